@@ -0,0 +1,9 @@
+-- 快速对局
+define_ruleset(
+    "quick",
+    function(rules)
+        rules:start_hp(3)
+        rules:opening_hand(6)
+        rules:max_real_point(4)
+        rules:skip_first_turn("draw")
+end)
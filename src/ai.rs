@@ -1,113 +1,127 @@
+use crate::card_text::describe_do_effect;
+use crate::choice_error::ChoiceError;
 use crate::choice_req::ChoiceReq;
 use crate::choice_res::ChoiceRes;
-use crate::common::EntryId;
+use crate::common::{EntryId, PlayerId};
 use crate::game::{Game, GamePhase};
+use crate::i18n::Dbg;
 use crate::player_actions::PlayerAction;
 use crate::targeting::Targeting;
 use crate::targeting::Targeting::TargetZone;
-use log::info;
+use crate::tr;
+use log::{info, warn};
 
 impl Game {
     pub fn ai_read_action_main(&mut self) {
-        while self.current_phase() == GamePhase::Main || self.current_phase() == GamePhase::Main2 {
-            let player_id = self.current_player();
-            info!("AI[{}] 开始主要阶段", player_id);
+        if !(self.current_phase() == GamePhase::Main || self.current_phase() == GamePhase::Main2) {
+            return;
+        }
+        let player_id = self.current_player();
+        info!("{}", tr!("AI[{}] 开始主要阶段", player_id));
 
-            let hand = self.current_hand();
-            let mut set_actions = Vec::new();
+        // 只根据自己可以看到的信息决定
+        let view = self.player_view(player_id);
+        let me = view.seat(player_id);
+        let empty_zone = me
+            .front_zones()
+            .find(|zone| zone.cards.is_empty())
+            .map(|zone| zone.id);
+        let mut set_actions = Vec::new();
 
-            for &card_id in &hand {
-                let card = self.get(card_id);
-                if self.check_cost(card_id) {
-                    let zones = self.current_zone();
-                    for zone in &zones {
-                        if let crate::game::Zone::FrontEnd { id, cards } = zone {
-                            if cards.is_empty() {
-                                set_actions.push((card_id, *id, card.card_info.cost));
-                                break;
-                            }
-                        }
-                    }
+        if let Some(zone_id) = empty_zone {
+            for card in me.hand.as_deref().unwrap_or_default() {
+                if let Some(cost) = card.cost()
+                    && self.check_cost(card.entry_id())
+                {
+                    set_actions.push((card.entry_id(), zone_id, cost));
                 }
             }
+        }
 
-            set_actions.sort_by_key(|x| x.2);
+        set_actions.sort_by_key(|x| x.2);
 
-            if set_actions.is_empty() {
-                info!("AI[{}] 费用不足或无空区域，pass", player_id);
-                break;
-            }
+        if set_actions.is_empty() {
+            info!("{}", tr!("AI[{}] 费用不足或无空区域，pass", player_id));
+            return;
+        }
 
-            for (card_id, zone_id, cost) in set_actions {
-                info!(
+        for (card_id, zone_id, cost) in set_actions {
+            info!(
+                "{}",
+                tr!(
                     "AI[{}] 尝试登场卡片 {} 到区域 {}，费用 {}",
-                    player_id, card_id, zone_id, cost
-                );
-                let action = PlayerAction::SetCard { card_id, zone_id };
-                self.deal_player_action(action);
-            }
-
-            break;
+                    player_id,
+                    card_id,
+                    zone_id,
+                    cost
+                )
+            );
+            let action = PlayerAction::SetCard { card_id, zone_id };
+            self.deal_player_action(action);
         }
     }
 
     pub fn ai_read_action_fight(&mut self) {
-        while self.current_phase() == GamePhase::Fight {
-            let player_id = self.current_player();
-            info!("AI[{}] 开始战斗阶段", player_id);
+        if self.current_phase() != GamePhase::Fight {
+            return;
+        }
+        let player_id = self.current_player();
+        info!("{}", tr!("AI[{}] 开始战斗阶段", player_id));
 
-            let attack_zones = self.get_attack_zones();
-            let attacked_zones = self.get_attacked_zones();
+        let attack_zones = self.get_attack_zones();
+        let view = self.player_view(player_id);
+        let opponents = self.opponents_of(player_id);
+        // 对手前场有卡的区域
+        let attacked_zones: Vec<EntryId> = opponents
+            .iter()
+            .flat_map(|&id| view.seat(id).front_zones())
+            .filter(|zone| !zone.cards.is_empty())
+            .map(|zone| zone.id)
+            .collect();
 
-            if attack_zones.is_empty() {
-                info!("AI[{}] 无可攻击区域，pass", player_id);
-                break;
-            }
+        if attack_zones.is_empty() {
+            info!("{}", tr!("AI[{}] 无可攻击区域，pass", player_id));
+            return;
+        }
 
-            for attack_zone in &attack_zones {
-                if let crate::game::Zone::FrontEnd {
-                    id: atk_zone_id, ..
-                } = attack_zone
-                {
-                    let has_front_end_target = attacked_zones.iter().any(|zone| {
-                        if let crate::game::Zone::FrontEnd { cards, .. } = zone {
-                            !cards.is_empty()
-                        } else {
-                            false
-                        }
+        // 前场没有卡的对手可以被直接攻击
+        let open_opponent = opponents.into_iter().find(|&id| {
+            view.seat(id)
+                .front_zones()
+                .all(|zone| zone.cards.is_empty())
+        });
+
+        for attack_zone in &attack_zones {
+            if let crate::game::Zone::FrontEnd {
+                id: atk_zone_id, ..
+            } = attack_zone
+            {
+                if let Some(target_id) = open_opponent {
+                    info!("{}", tr!("AI[{}] 直接攻击玩家[{}]", player_id, target_id));
+                    self.deal_player_action(PlayerAction::AttackCard {
+                        source: TargetZone(*atk_zone_id),
+                        target: Targeting::TargetPlayer(target_id),
                     });
+                    continue;
+                }
 
-                    if has_front_end_target {
-                        for target_zone in &attacked_zones {
-                            if let crate::game::Zone::FrontEnd {
-                                id: target_zone_id,
-                                cards,
-                            } = target_zone
-                            {
-                                if !cards.is_empty() {
-                                    info!(
-                                        "AI[{}] 攻击区域 {} -> {}",
-                                        player_id, atk_zone_id, target_zone_id
-                                    );
-                                    self.deal_player_action(PlayerAction::AttackCard {
-                                        source: TargetZone(*atk_zone_id),
-                                        target: TargetZone(*target_zone_id),
-                                    });
-                                    break;
-                                }
-                            }
-                        }
-                    } else {
-                        info!("AI[{}] 直接攻击对手", player_id);
-                        self.deal_player_action(PlayerAction::AttackCard {
-                            source: TargetZone(*atk_zone_id),
-                            target: Targeting::TargetPlayerOpponent,
-                        });
-                    }
+                // 所有对手前场都有卡时攻击第一个有卡的区域
+                if let Some(&target_zone_id) = attacked_zones.first() {
+                    info!(
+                        "{}",
+                        tr!(
+                            "AI[{}] 攻击区域 {} -> {}",
+                            player_id,
+                            atk_zone_id,
+                            target_zone_id
+                        )
+                    );
+                    self.deal_player_action(PlayerAction::AttackCard {
+                        source: TargetZone(*atk_zone_id),
+                        target: TargetZone(target_zone_id),
+                    });
                 }
             }
-
-            break;
         }
     }
 
@@ -117,33 +131,35 @@ impl Game {
 
         if real_point > 0 {
             info!(
-                "AI[{}] 消耗所有RealPoint造成伤害，RealPoint={}",
-                player_id, real_point
+                "{}",
+                tr!(
+                    "AI[{}] 消耗所有RealPoint造成伤害，RealPoint={}",
+                    player_id,
+                    real_point
+                )
             );
             return ChoiceRes::FightDamageByRealPoint(real_point);
         }
 
-        info!("AI[{}] 放弃伤害，获得RealPoint", player_id);
+        info!("{}", tr!("AI[{}] 放弃伤害，获得RealPoint", player_id));
         ChoiceRes::None
     }
 
     pub fn ai_read_choice(&mut self, choice: ChoiceReq) -> ChoiceRes {
         let player_id = self.current_player();
         match choice {
+            ChoiceReq::FightDamage => self.ai_read_fight_damage(),
             ChoiceReq::Cost(card_id) => {
-                if !self.check_cost(card_id) {
-                    info!("AI[{}] 无法支付费用，取消操作", player_id);
-                    self.set_rollback(card_id);
-                    return ChoiceRes::None;
-                }
-
                 let card = self.get(card_id);
                 let cost = card.card_info.cost;
                 let hand = self.current_hand();
                 let real_point = self.current_real_point();
                 let current_cost_len = self.current_cost().len();
                 // Cost区剩余可用槽位
-                let available_cost_slots = 6usize.saturating_sub(current_cost_len);
+                let available_cost_slots = self
+                    .ruleset()
+                    .max_cost_size
+                    .saturating_sub(current_cost_len);
 
                 let mut use_hand: Vec<EntryId> = Vec::new();
                 let mut remaining_cost = cost;
@@ -169,8 +185,13 @@ impl Game {
                 };
 
                 info!(
-                    "AI[{}] 支付费用: 手牌 {:?}, RealPoint {}",
-                    player_id, use_hand, use_real_point
+                    "{}",
+                    tr!(
+                        "AI[{}] 支付费用: 手牌 {}, RealPoint {}",
+                        player_id,
+                        Dbg(&use_hand),
+                        use_real_point
+                    )
                 );
 
                 ChoiceRes::Cost {
@@ -178,21 +199,61 @@ impl Game {
                     real_point: use_real_point,
                 }
             }
+            ChoiceReq::Reuse {
+                player_id: target_id,
+                limit,
+            } => ChoiceRes::Reuse(self.ai_read_reuse_choice(target_id, limit)),
+            ChoiceReq::Mulligan(player_id) => {
+                // 放回费用高于起手卡数的卡
+                let opening_hand = self.ruleset().opening_hand;
+                let cards: Vec<EntryId> = self
+                    .hand(player_id)
+                    .into_iter()
+                    .filter(|&card| self.get(card).card_info.cost > opening_hand)
+                    .collect();
+                info!("{}", tr!("AI[{}] 起手调度 放回 {}", player_id, Dbg(&cards)));
+                ChoiceRes::Mulligan(cards)
+            }
+            ChoiceReq::OrAction(effects) => {
+                // 执行第一项
+                let text = effects.first().map(describe_do_effect);
+                info!(
+                    "{}",
+                    tr!("AI[{}] 选择执行 {}", player_id, text.unwrap_or_default())
+                );
+                ChoiceRes::OrAction(0)
+            }
         }
     }
 
-    pub fn ai_read_reuse_choice(&mut self, targeting: Targeting, limit: usize) -> Vec<EntryId> {
+    pub fn ai_read_choice_error(&mut self, choice: &ChoiceReq, choice_error: &ChoiceError) {
+        warn!(
+            "{}",
+            tr!(
+                "AI[{}] 选择{}不合法: {}",
+                self.current_player(),
+                Dbg(&choice),
+                choice_error
+            )
+        );
+    }
+
+    pub fn ai_read_reuse_choice(&mut self, target_id: PlayerId, limit: usize) -> Vec<EntryId> {
         let player_id = self.current_player();
-        let costs = if let Targeting::TargetPlayerSelf = targeting {
-            self.current_cost()
-        } else {
-            self.next_cost()
-        };
+        let costs = self.cost_zone(target_id);
 
         let take = costs.len().min(limit);
         let result: Vec<EntryId> = costs.iter().take(take).cloned().collect();
 
-        info!("AI[{}] 回收卡片 {:?} (限制 {})", player_id, result, limit);
+        info!(
+            "{}",
+            tr!(
+                "AI[{}] 回收卡片 {} (限制 {})",
+                player_id,
+                Dbg(&result),
+                limit
+            )
+        );
 
         result
     }
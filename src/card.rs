@@ -1,12 +1,19 @@
-use crate::common::{CardInfoId, EntryId, IdGenerator};
+use crate::card_meta::{CardMeta, Rarity};
+use crate::common::{CardInfoId, EntryId, IdGenerator, PlayerId};
 use crate::effect::{Effect, EffectBuilder};
-use crate::lua_api::LuaApi;
 use crate::player::PlayerDesk;
+use crate::sandbox::run_limited;
 use log::warn;
-use mlua::{Function, Lua, UserData, UserDataMethods};
+use mlua::{Function, UserData, UserDataMethods};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 全部卡片定义 多局游戏共用一份 重新读取时才复制
+pub type CardDefs = Arc<HashMap<CardInfoId, CardInfo>>;
 
 /// 卡片信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CardInfo {
     // id
     id: CardInfoId,
@@ -16,8 +23,12 @@ pub struct CardInfo {
     pub cost: usize,
     // 攻击力
     pub ack: usize,
+    // 规则文本 为空时由效果生成
+    pub text: String,
     // 效果
     pub effects: Vec<Effect>,
+    // 卡包和稀有度等收藏信息
+    pub meta: CardMeta,
 }
 
 #[derive(Debug, Clone)]
@@ -30,17 +41,29 @@ pub struct CardInfoBuilder {
     cost: usize,
     // 攻击力
     ack: usize,
+    // 规则文本
+    text: String,
     // 效果
     effects: Vec<Effect>,
+    // 收藏信息 默认从id中解析
+    meta: CardMeta,
+}
+
+impl CardInfo {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
 }
 
 impl CardInfoBuilder {
     pub fn new(id: String) -> Self {
         Self {
+            meta: CardMeta::from_id(&id).unwrap_or_default(),
             id,
             name: "".to_string(),
             cost: 0,
             ack: 0,
+            text: "".to_string(),
             effects: Vec::new(),
         }
     }
@@ -51,7 +74,9 @@ impl CardInfoBuilder {
             name: self.name,
             cost: self.cost,
             ack: self.ack,
+            text: self.text,
             effects: self.effects,
+            meta: self.meta,
         }
     }
 }
@@ -59,27 +84,59 @@ impl CardInfoBuilder {
 impl UserData for CardInfoBuilder {
     fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
         // 添加基础信息的方法
-        methods.add_method_mut("name", |lua, this, name: String| {
+        methods.add_method_mut("name", |_lua, this, name: String| {
             this.name = name;
             Ok(())
         });
 
-        methods.add_method_mut("cost", |lua, this, cost: usize| {
+        methods.add_method_mut("cost", |_lua, this, cost: usize| {
             this.cost = cost;
             Ok(())
         });
 
-        methods.add_method_mut("ack", |lua, this, ack: usize| {
+        methods.add_method_mut("ack", |_lua, this, ack: usize| {
             this.ack = ack;
             Ok(())
         });
 
+        methods.add_method_mut("text", |_lua, this, text: String| {
+            this.text = text;
+            Ok(())
+        });
+
+        // 收藏信息 覆盖从id中解析的值
+        methods.add_method_mut("set_code", |_lua, this, set_code: String| {
+            this.meta.set_code = set_code;
+            Ok(())
+        });
+
+        methods.add_method_mut("series", |_lua, this, series: String| {
+            this.meta.series = series;
+            Ok(())
+        });
+
+        methods.add_method_mut("number", |_lua, this, number: usize| {
+            this.meta.number = number;
+            Ok(())
+        });
+
+        methods.add_method_mut("rarity", |_lua, this, name: String| {
+            this.meta.rarity = Rarity::from_name(&name)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("未知的稀有度 {}", name)))?;
+            Ok(())
+        });
+
+        methods.add_method_mut("version", |_lua, this, version: String| {
+            this.meta.version = Some(version);
+            Ok(())
+        });
+
         // 添加效果的方法
         methods.add_method_mut("reg_effect", |lua, this, (id, func): (String, Function)| {
             let builder = EffectBuilder::new(id);
 
             let effect_ud = lua.create_userdata(builder)?;
-            func.call::<()>(effect_ud.clone())?;
+            run_limited(lua, || func.call::<()>(effect_ud.clone()))?;
 
             let effect_builder = effect_ud.take::<EffectBuilder>()?;
             this.effects.push(effect_builder.build());
@@ -90,33 +147,45 @@ impl UserData for CardInfoBuilder {
 }
 
 /// 卡片对象
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Card {
     pub entry_id: EntryId,
     pub card_info: CardInfo,
+    // 持有者 卡片来自这个玩家的卡组
+    pub owner: PlayerId,
+    // 控制者 卡片效果以这个玩家为自己结算
+    pub controller: PlayerId,
     // 攻击计数器
     pub attack_counter: usize,
     // 攻击最大次数计数器
     pub attack_max: usize,
+    // 背面朝上 只有控制者可以看到 旧存档中没有这一项
+    #[serde(default)]
+    pub face_down: bool,
 }
 
 impl Card {
     // 初始化列表
     pub fn init(
         player_desk: PlayerDesk,
-        lua_api: &LuaApi,
+        owner: PlayerId,
+        card_defs: &HashMap<CardInfoId, CardInfo>,
         id_generator: &mut IdGenerator,
     ) -> Vec<Card> {
         let mut res = Vec::new();
         for card_info_id in player_desk.0 {
-            if let Some(card_info) = lua_api.cards.get(&card_info_id) {
+            if let Some(card_info) = card_defs.get(&card_info_id) {
                 res.push(Card {
                     entry_id: id_generator.next(),
                     card_info: card_info.clone(),
+                    owner,
+                    controller: owner,
                     attack_counter: 0,
                     attack_max: 1,
+                    face_down: false,
                 });
             } else {
+                // 卡组读取时已经检查过卡片id 这里只会出现在手工构造的卡组中
                 warn!("Card with id {} not found", card_info_id);
             }
         }
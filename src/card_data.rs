@@ -0,0 +1,251 @@
+use crate::card::{CardInfo, CardInfoBuilder};
+use crate::card_meta::Rarity;
+use crate::effect::{Action, DoEffect, EffectBuilder, WindowsTag};
+use crate::game::GamePhase;
+use crate::load_error::LoadError;
+use crate::lua_api::LuaApi;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// 不需要脚本的卡片文件 JSON 为 `{"cards": [...]}`，TOML 为多个 `[[cards]]`
+///
+/// ```toml
+/// [[cards]]
+/// id = "S000-A-002"
+/// name = "测试卡002"
+/// cost = 1
+/// ack = 50
+///
+/// [[cards.effects]]
+/// id = "e1"
+/// window = "set"
+/// action = "draw"
+/// amount = 1
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CardFile {
+    cards: Vec<CardData>,
+}
+
+// 一张卡片 收藏信息没有填写时从id中解析
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CardData {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    cost: usize,
+    #[serde(default)]
+    ack: usize,
+    #[serde(default)]
+    text: String,
+    set_code: Option<String>,
+    series: Option<String>,
+    number: Option<usize>,
+    rarity: Option<String>,
+    version: Option<String>,
+    #[serde(default)]
+    effects: Vec<EffectData>,
+}
+
+// 一个效果 动作和 Lua 中 effect 的方法同名
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EffectData {
+    id: String,
+    window: String,
+    action: String,
+    // draw/damage 的数量
+    amount: Option<usize>,
+    // skip_phase/add_phase 的阶段
+    phase: Option<String>,
+}
+
+/// 是否是卡片数据文件
+pub fn is_card_data(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("json" | "toml")
+    )
+}
+
+/// 读取一个 JSON/TOML 卡片文件 和脚本定义的卡片一样写入 `api.cards`
+pub fn load_card_data(api: &mut LuaApi, path: &Path) -> Result<(), LoadError> {
+    let code = fs::read_to_string(path).map_err(|e| LoadError::io(path, e))?;
+    let data_error = |card_id: Option<&str>, message: String| LoadError::CardData {
+        path: path.to_path_buf(),
+        card_id: card_id.map(|id| id.to_string()),
+        message,
+    };
+    let file: CardFile = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&code).map_err(|e| data_error(None, e.to_string()))?,
+        _ => toml::from_str(&code).map_err(|e| data_error(None, e.to_string()))?,
+    };
+    // 先全部检查 出错时不写入这个文件的任何卡片
+    let mut cards = Vec::new();
+    for data in file.cards {
+        let id = data.id.clone();
+        if api.cards.contains_key(&id) || cards.iter().any(|card: &CardInfo| card.id() == id) {
+            return Err(data_error(Some(&id), format!("卡片[{}]重复定义", id)));
+        }
+        cards.push(
+            data.into_card_info()
+                .map_err(|e| data_error(Some(&id), e))?,
+        );
+    }
+    for card_info in cards {
+        api.cards.insert(card_info.id().to_string(), card_info);
+    }
+    Ok(())
+}
+
+impl CardData {
+    fn into_card_info(self) -> Result<CardInfo, String> {
+        let mut card_info = CardInfoBuilder::new(self.id).build();
+        card_info.name = self.name;
+        card_info.cost = self.cost;
+        card_info.ack = self.ack;
+        card_info.text = self.text;
+        let meta = &mut card_info.meta;
+        if let Some(set_code) = self.set_code {
+            meta.set_code = set_code;
+        }
+        if let Some(series) = self.series {
+            meta.series = series;
+        }
+        if let Some(number) = self.number {
+            meta.number = number;
+        }
+        if let Some(rarity) = self.rarity {
+            meta.rarity =
+                Rarity::from_name(&rarity).ok_or_else(|| format!("未知的稀有度 {}", rarity))?;
+        }
+        meta.version = self.version;
+        for data in self.effects {
+            let mut effect = EffectBuilder::new(data.id.clone()).build();
+            effect.windows_tag = WindowsTag::from_name(&data.window)
+                .ok_or_else(|| format!("效果[{}] 未知的窗口 {}", data.id, data.window))?;
+            effect.do_effect = DoEffect::action(data.action()?);
+            card_info.effects.push(effect);
+        }
+        Ok(card_info)
+    }
+}
+
+impl EffectData {
+    fn action(&self) -> Result<Action, String> {
+        let amount = || {
+            self.amount
+                .ok_or_else(|| format!("效果[{}] {} 需要 amount", self.id, self.action))
+        };
+        let phase = || {
+            let name = self
+                .phase
+                .as_deref()
+                .ok_or_else(|| format!("效果[{}] {} 需要 phase", self.id, self.action))?;
+            GamePhase::from_name(name)
+                .ok_or_else(|| format!("效果[{}] 未知的阶段 {}", self.id, name))
+        };
+        match self.action.as_str() {
+            "draw" => Ok(Action::Draw(amount()?)),
+            "damage" => Ok(Action::Damage(amount()?)),
+            "skip_phase" => Ok(Action::SkipPhase(phase()?)),
+            "add_phase" => Ok(Action::AddPhase(phase()?)),
+            action => Err(format!("效果[{}] 未知的动作 {}", self.id, action)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_meta::Rarity;
+    use crate::targeting::Targeting;
+    use std::path::PathBuf;
+
+    // 写入临时目录后读取
+    fn load(name: &str, code: &str, api: &mut LuaApi) -> Result<(), LoadError> {
+        let dir = std::env::temp_dir().join(format!("card_data_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path: PathBuf = dir.join(name);
+        fs::write(&path, code).unwrap();
+        let result = load_card_data(api, &path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_load_toml_and_json() {
+        let mut api = LuaApi::new();
+        let toml = r#"
+[[cards]]
+id = "S001-B-007"
+name = "草地"
+cost = 1
+ack = 50
+rarity = "r"
+
+[[cards.effects]]
+id = "e1"
+window = "set"
+action = "draw"
+amount = 2
+
+[[cards]]
+id = "S001-B-008"
+"#;
+        load("vanilla.toml", toml, &mut api).unwrap();
+        let card = &api.cards["S001-B-007"];
+        assert_eq!((card.name.as_str(), card.cost, card.ack), ("草地", 1, 50));
+        assert_eq!(card.meta.set_code, "S001");
+        assert_eq!(card.meta.number, 7);
+        assert_eq!(card.meta.rarity, Rarity::Rare);
+        assert_eq!(card.effects[0].windows_tag, WindowsTag::OnSet);
+        assert_eq!(
+            card.effects[0].do_effect,
+            DoEffect::Action {
+                source: Targeting::None,
+                targeting: Targeting::TargetPlayerSelf,
+                action: Action::Draw(2),
+            }
+        );
+
+        let json = r#"{"cards": [{"id": "X", "cost": 3, "effects": [
+            {"id": "e1", "window": "phase:draw", "action": "skip_phase", "phase": "fight"}
+        ]}]}"#;
+        load("vanilla.json", json, &mut api).unwrap();
+        assert_eq!(
+            api.cards["X"].effects[0].do_effect,
+            DoEffect::action(Action::SkipPhase(GamePhase::Fight))
+        );
+        assert_eq!(api.cards.len(), 3);
+    }
+
+    #[test]
+    fn test_card_data_errors() {
+        let mut api = LuaApi::new();
+        api.cards.insert(
+            "A".to_string(),
+            CardInfoBuilder::new("A".to_string()).build(),
+        );
+        for code in [
+            // 和已有的卡片重复
+            "[[cards]]\nid = \"A\"",
+            // 未知的字段
+            "[[cards]]\nid = \"B\"\natk = 1",
+            // 缺少数量
+            "[[cards]]\nid = \"B\"\n[[cards.effects]]\nid = \"e\"\nwindow = \"set\"\naction = \"draw\"",
+            // 未知的动作
+            "[[cards]]\nid = \"B\"\n[[cards.effects]]\nid = \"e\"\nwindow = \"set\"\naction = \"fly\"",
+        ] {
+            assert!(matches!(
+                load("bad.toml", code, &mut api),
+                Err(LoadError::CardData { .. })
+            ));
+        }
+        assert_eq!(api.cards.len(), 1);
+    }
+}
@@ -1,12 +1,59 @@
+use crate::card_data::{is_card_data, load_card_data};
+use crate::i18n::localize_cards;
+use crate::load_error::{LoadError, LoadReport};
 use crate::lua_api::LuaApi;
+use crate::sandbox::run_limited;
 use mlua::Lua;
 use std::fs;
+use std::path::Path;
 
-pub fn load_cards(lua: &Lua, api: &mut LuaApi) {
-    api.install(lua).unwrap();
+/// 读取卡片目录下的卡片脚本和卡片数据文件
+pub fn load_cards(
+    lua: &Lua,
+    api: &mut LuaApi,
+    dir: &Path,
+    report: &mut LoadReport,
+) -> Result<(), LoadError> {
+    LuaApi::install(lua).map_err(|e| LoadError::script(dir, None, &e))?;
 
-    for file in fs::read_dir("cards").unwrap() {
-        let code = fs::read_to_string(file.unwrap().path()).unwrap();
-        lua.load(&code).exec().unwrap();
+    let files = match fs::read_dir(dir) {
+        Ok(files) => files,
+        Err(e) => return report.fail(LoadError::io(dir, e)),
+    };
+    for file in files {
+        let path = match file {
+            Ok(file) => file.path(),
+            Err(e) => {
+                report.fail(LoadError::io(dir, e))?;
+                continue;
+            }
+        };
+        // JSON/TOML 为不需要脚本的卡片
+        let result = if is_card_data(&path) {
+            load_card_data(api, &path)
+        } else {
+            load_card_file(lua, api, &path)
+        };
+        match result {
+            Ok(()) => report.loaded.push(path),
+            Err(load_error) => report.fail(load_error)?,
+        }
     }
+    // 卡片名称使用选择的语言
+    localize_cards(api.cards.values_mut());
+    Ok(())
+}
+
+// 执行一个卡片脚本
+pub fn load_card_file(lua: &Lua, api: &mut LuaApi, path: &Path) -> Result<(), LoadError> {
+    let code = fs::read_to_string(path).map_err(|e| LoadError::io(path, e))?;
+    api.defining_card = None;
+    api.load_with(lua, || {
+        run_limited(lua, || {
+            lua.load(&code)
+                .set_name(format!("@{}", path.display()))
+                .exec()
+        })
+    })
+    .map_err(|e| LoadError::script(path, api.defining_card.take(), &e))
 }
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 稀有度
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Rarity {
+    #[default]
+    Normal,
+    Rare,
+    SuperRare,
+    UltraRare,
+}
+
+impl Rarity {
+    // 脚本中使用的稀有度名称
+    pub fn from_name(name: &str) -> Option<Rarity> {
+        match name.to_ascii_lowercase().as_str() {
+            "n" => Some(Rarity::Normal),
+            "r" => Some(Rarity::Rare),
+            "sr" => Some(Rarity::SuperRare),
+            "ur" => Some(Rarity::UltraRare),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rarity::Normal => "N",
+            Rarity::Rare => "R",
+            Rarity::SuperRare => "SR",
+            Rarity::UltraRare => "UR",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 卡片的收藏信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardMeta {
+    // 卡包编号 如 S000
+    pub set_code: String,
+    // 系列 如 A
+    pub series: String,
+    // 卡包内的编号
+    pub number: usize,
+    // 稀有度
+    pub rarity: Rarity,
+    // 加入的版本
+    pub version: Option<String>,
+}
+
+impl CardMeta {
+    /// 从 `卡包-系列-编号` 格式的卡片id中解析 如 `S000-A-001`
+    pub fn from_id(id: &str) -> Option<CardMeta> {
+        let mut parts = id.split('-');
+        let (Some(set_code), Some(series), Some(number), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        if set_code.is_empty() || series.is_empty() {
+            return None;
+        }
+        Some(CardMeta {
+            set_code: set_code.to_string(),
+            series: series.to_string(),
+            number: number.parse().ok()?,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_from_id() {
+        let meta = CardMeta::from_id("S000-A-001").unwrap();
+        assert_eq!(meta.set_code, "S000");
+        assert_eq!(meta.series, "A");
+        assert_eq!(meta.number, 1);
+        assert_eq!(meta.rarity, Rarity::Normal);
+        assert!(CardMeta::from_id("test-card").is_none());
+        assert!(CardMeta::from_id("S000-A-001-X").is_none());
+        assert!(CardMeta::from_id("-A-001").is_none());
+    }
+}
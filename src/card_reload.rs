@@ -0,0 +1,163 @@
+use crate::card::CardInfo;
+use crate::card_loader::load_cards;
+use crate::common::CardInfoId;
+use crate::load_error::{LoadError, LoadReport};
+use crate::lua_api::LuaApi;
+use crate::sandbox::{SandboxLimits, new_lua};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 重新读取卡片脚本的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardReload {
+    // 新增的卡片定义
+    pub added: Vec<CardInfoId>,
+    // 内容变化的卡片定义
+    pub changed: Vec<CardInfoId>,
+    // 被删除的卡片定义 已有的卡片实体保留原来的定义
+    pub removed: Vec<CardInfoId>,
+    // 更新了定义的卡片实体数
+    pub updated: usize,
+    // 在场上而没有更新的卡片实体数
+    pub skipped: usize,
+}
+
+/// 卡片目录和运行脚本的沙盒限制 重新读取时和启动时使用同样的设置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardSource {
+    pub dir: PathBuf,
+    pub limits: SandboxLimits,
+}
+
+impl Default for CardSource {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("cards"),
+            limits: SandboxLimits::default(),
+        }
+    }
+}
+
+/// 监视卡片目录 按文件的修改时间判断脚本是否变化
+#[derive(Debug, Clone)]
+pub struct CardWatcher {
+    dir: PathBuf,
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl CardWatcher {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            stamps: scan(dir),
+        }
+    }
+
+    // 目录中的文件和上次检查时相比是否有变化
+    pub fn changed(&mut self) -> bool {
+        let stamps = scan(&self.dir);
+        if stamps == self.stamps {
+            return false;
+        }
+        self.stamps = stamps;
+        true
+    }
+}
+
+// 目录中的文件及修改时间 按路径排序
+fn scan(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut stamps: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|file| {
+            let modified = file.metadata().and_then(|meta| meta.modified()).ok();
+            (file.path(), modified)
+        })
+        .collect();
+    stamps.sort();
+    stamps
+}
+
+/// 比较新旧卡片定义
+pub fn diff_card_defs(
+    old: &HashMap<CardInfoId, CardInfo>,
+    new: &HashMap<CardInfoId, CardInfo>,
+) -> CardReload {
+    let mut reload = CardReload::default();
+    for (id, card_info) in new {
+        match old.get(id) {
+            None => reload.added.push(id.clone()),
+            Some(old_info) if old_info != card_info => reload.changed.push(id.clone()),
+            _ => {}
+        }
+    }
+    reload.removed = old
+        .keys()
+        .filter(|id| !new.contains_key(*id))
+        .cloned()
+        .collect();
+    reload.added.sort();
+    reload.changed.sort();
+    reload.removed.sort();
+    reload
+}
+
+/// 重新执行全部卡片脚本
+pub fn reload_card_defs(source: &CardSource) -> Result<HashMap<CardInfoId, CardInfo>, LoadError> {
+    let lua = new_lua(source.limits).map_err(|e| LoadError::script(&source.dir, None, &e))?;
+    let mut api = LuaApi::new();
+    load_cards(&lua, &mut api, &source.dir, &mut LoadReport::new(false))?;
+    Ok(api.cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardInfoBuilder;
+
+    fn defs(cards: &[(&str, &str)]) -> HashMap<CardInfoId, CardInfo> {
+        cards
+            .iter()
+            .map(|(id, name)| {
+                let mut card_info = CardInfoBuilder::new(id.to_string()).build();
+                card_info.name = name.to_string();
+                (id.to_string(), card_info)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_card_defs() {
+        let old = defs(&[("A", "a"), ("B", "b")]);
+        let new = defs(&[("A", "a2"), ("C", "c")]);
+        let reload = diff_card_defs(&old, &new);
+        assert_eq!(reload.added, vec!["C".to_string()]);
+        assert_eq!(reload.changed, vec!["A".to_string()]);
+        assert_eq!(reload.removed, vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn test_reload_uses_source() {
+        let dir = std::env::temp_dir().join(format!("card_reload_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a.lua"),
+            "local t = {} for i = 1, 100000 do t[i] = i end",
+        )
+        .unwrap();
+        fs::write(dir.join("b.toml"), "[[cards]]\nid = \"T001\"").unwrap();
+        let mut source = CardSource {
+            dir: dir.clone(),
+            limits: SandboxLimits::default(),
+        };
+        let defs = reload_card_defs(&source).unwrap();
+        assert!(defs.contains_key("T001"));
+        // 超过限制时读取失败
+        source.limits.instructions = 1000;
+        assert!(reload_card_defs(&source).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
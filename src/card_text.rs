@@ -0,0 +1,236 @@
+use crate::card::CardInfo;
+use crate::effect::{Action, Condition, DoEffect, Effect, WindowsTag};
+use crate::game::GamePhase;
+use crate::targeting::Targeting;
+use crate::tr;
+
+// 阶段的显示名称
+pub fn describe_phase(phase: &GamePhase) -> String {
+    match phase {
+        GamePhase::Start => tr!("回合开始"),
+        GamePhase::Draw => tr!("抽卡"),
+        GamePhase::Standby => tr!("准备"),
+        GamePhase::Reuse => tr!("回收"),
+        GamePhase::Main => tr!("主要"),
+        GamePhase::Fight => tr!("战斗"),
+        GamePhase::Main2 => tr!("主要2"),
+        GamePhase::End => tr!("结束"),
+    }
+}
+
+/// 发动时机的说明 如 `登场时`
+pub fn describe_window(tag: &WindowsTag) -> String {
+    match tag {
+        WindowsTag::None => String::new(),
+        WindowsTag::OnSelfStart => tr!("自己回合开始时"),
+        WindowsTag::OnOpponentStart => tr!("对手回合开始时"),
+        WindowsTag::OnStart => tr!("回合开始时"),
+        WindowsTag::OnCost => tr!("暴露时"),
+        WindowsTag::OnSet => tr!("登场时"),
+        WindowsTag::DuringMain => tr!("主要阶段"),
+        WindowsTag::OnAttack => tr!("攻击时"),
+        WindowsTag::OnPhase(phase) => tr!("进入{}阶段时", describe_phase(phase)),
+    }
+}
+
+fn describe_condition(condition: &Condition) -> String {
+    match condition {
+        Condition::None => String::new(),
+    }
+}
+
+fn describe_target(targeting: &Targeting) -> String {
+    match targeting {
+        Targeting::None => String::new(),
+        Targeting::TargetPlayerSelf => tr!("自己"),
+        Targeting::TargetPlayerOpponent => tr!("对手"),
+        Targeting::TargetPlayer(player_id) => tr!("玩家[{}]", player_id),
+        Targeting::TargetCard(card_id) => tr!("卡片[{}]", card_id),
+        Targeting::TargetZone(zone_id) => tr!("区域[{}]", zone_id),
+    }
+}
+
+fn describe_action(targeting: &Targeting, action: &Action) -> String {
+    match action {
+        Action::None => String::new(),
+        Action::Draw(num) => match targeting {
+            Targeting::TargetPlayerSelf | Targeting::None => tr!("抽{}张卡", num),
+            _ => tr!("{}抽{}张卡", describe_target(targeting), num),
+        },
+        Action::Set { .. } => tr!("登场一张卡"),
+        Action::Damage(num) => tr!("对{}造成{}点伤害", describe_target(targeting), num),
+        Action::AddRealPoint(num) => tr!("获得{}点RealPoint", num),
+        Action::UseRealPoint(num) => tr!("消耗{}点RealPoint", num),
+        Action::FightDestroy { zone_id } => tr!("战斗破坏区域[{}]的卡", zone_id),
+        Action::AttackCounterUp(_, num) => tr!("攻击次数增加{}", num),
+        Action::AttackCountDown(_, num) => tr!("攻击次数减少{}", num),
+        Action::AskingReuse(num) => tr!("从Cost区回收{}张卡", num),
+        Action::Reuse(cards) => tr!("回收{}张卡", cards.len()),
+        Action::SkipPhase(phase) => tr!("跳过本回合的{}阶段", describe_phase(phase)),
+        Action::AddPhase(phase) => tr!("在本阶段后追加{}阶段", describe_phase(phase)),
+    }
+}
+
+/// 效果动作的说明 如 `抽1张卡`
+pub fn describe_do_effect(do_effect: &DoEffect) -> String {
+    let join = |effects: &Vec<DoEffect>| {
+        effects
+            .iter()
+            .map(describe_do_effect)
+            .filter(|text| !text.is_empty())
+            .reduce(|all, text| tr!("{}；{}", all, text))
+            .unwrap_or_default()
+    };
+    match do_effect {
+        DoEffect::None => String::new(),
+        DoEffect::Action {
+            targeting, action, ..
+        } => describe_action(targeting, action),
+        DoEffect::AndAction(effects) => join(effects),
+        DoEffect::OrAction(effects) => tr!("任意执行：{}", join(effects)),
+    }
+}
+
+/// 自动生成的效果说明 如 `登场时：抽1张卡`
+pub fn describe_effect(effect: &Effect) -> String {
+    let window = describe_window(&effect.windows_tag);
+    let condition = describe_condition(&effect.condition);
+    let do_effect = describe_do_effect(&effect.do_effect);
+    match (window.is_empty(), condition.is_empty()) {
+        (true, true) => do_effect,
+        (true, false) => tr!("{}：{}", condition, do_effect),
+        (false, true) => tr!("{}：{}", window, do_effect),
+        (false, false) => tr!("{}，{}：{}", window, condition, do_effect),
+    }
+}
+
+/// 卡片的规则文本 没有设置时由效果生成 每个效果一行
+pub fn rules_text(card_info: &CardInfo) -> String {
+    if !card_info.text.is_empty() {
+        return card_info.text.clone();
+    }
+    card_info
+        .effects
+        .iter()
+        .map(describe_effect)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 命令行中显示的卡片详情
+pub fn card_details(card_info: &CardInfo) -> String {
+    let mut details = tr!(
+        "[{}] {} 费用{} 攻击力{} {}",
+        card_info.id(),
+        card_info.name,
+        card_info.cost,
+        card_info.ack,
+        card_info.meta.rarity
+    );
+    let text = rules_text(card_info);
+    if !text.is_empty() {
+        details.push('\n');
+        details.push_str(&text);
+    }
+    details
+}
+
+// 导出的字段中不能有制表符和换行
+fn export_field(text: &str) -> String {
+    text.replace('\t', " ").replace('\n', "\\n")
+}
+
+/// 导出卡片的文本 制表符分隔 供卡面模板使用
+///
+/// 列为 `id name cost ack set_code series number rarity text`，规则文本中的换行写作 `\n`
+pub fn export_card_texts(cards: &[&CardInfo]) -> String {
+    let mut out = String::from("id\tname\tcost\tack\tset_code\tseries\tnumber\trarity\ttext\n");
+    for card_info in cards {
+        let meta = &card_info.meta;
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            export_field(card_info.id()),
+            export_field(&card_info.name),
+            card_info.cost,
+            card_info.ack,
+            export_field(&meta.set_code),
+            export_field(&meta.series),
+            meta.number,
+            meta.rarity,
+            export_field(&rules_text(card_info))
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardInfoBuilder;
+    use crate::effect::EffectBuilder;
+    use crate::lua_api::LuaApi;
+
+    fn effect(windows_tag: WindowsTag, targeting: Targeting, action: Action) -> Effect {
+        let mut effect = EffectBuilder::new("e1".to_string()).build();
+        effect.windows_tag = windows_tag;
+        effect.do_effect = DoEffect::Action {
+            source: Targeting::None,
+            targeting,
+            action,
+        };
+        effect
+    }
+
+    #[test]
+    fn test_describe_effect() {
+        let draw = effect(
+            WindowsTag::OnSet,
+            Targeting::TargetPlayerSelf,
+            Action::Draw(1),
+        );
+        assert_eq!(describe_effect(&draw), "登场时：抽1张卡");
+        let skip = effect(
+            WindowsTag::OnPhase(GamePhase::Draw),
+            Targeting::None,
+            Action::SkipPhase(GamePhase::Fight),
+        );
+        assert_eq!(
+            describe_effect(&skip),
+            "进入抽卡阶段时：跳过本回合的战斗阶段"
+        );
+        let both = DoEffect::AndAction(vec![
+            draw.do_effect.clone(),
+            effect(
+                WindowsTag::None,
+                Targeting::TargetPlayerOpponent,
+                Action::Damage(2),
+            )
+            .do_effect,
+        ]);
+        assert_eq!(describe_do_effect(&both), "抽1张卡；对对手造成2点伤害");
+    }
+
+    #[test]
+    fn test_rules_text_and_export() {
+        let mut card_info = CardInfoBuilder::new("S000-A-001".to_string()).build();
+        card_info.name = "测试卡".to_string();
+        card_info.effects.push(effect(
+            WindowsTag::OnSet,
+            Targeting::TargetPlayerSelf,
+            Action::Draw(1),
+        ));
+        assert_eq!(rules_text(&card_info), "登场时：抽1张卡");
+
+        card_info.text = "登场时：抽1张卡。\n这张卡不能被破坏。".to_string();
+        assert_eq!(rules_text(&card_info), card_info.text);
+
+        let mut api = LuaApi::new();
+        api.cards.insert(card_info.id().to_string(), card_info);
+        let export = export_card_texts(&api.find_cards(|_| true));
+        assert_eq!(
+            export.lines().nth(1),
+            Some("S000-A-001\t测试卡\t0\t0\tS000\tA\t1\tN\t登场时：抽1张卡。\\n这张卡不能被破坏。")
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::choice_error::ChoiceError;
+use crate::choice_req::ChoiceReq;
+use crate::choice_res::ChoiceRes;
+use crate::common::EntryId;
+use crate::game::Game;
+use std::collections::HashSet;
+
+// 检查卡片没有被重复选择 并且都在可选范围内
+pub fn check_cards(
+    cards: &[EntryId],
+    range: &[EntryId],
+    missing: fn(EntryId) -> ChoiceError,
+) -> Result<(), ChoiceError> {
+    let mut seen = HashSet::new();
+    for &card in cards {
+        if !seen.insert(card) {
+            return Err(ChoiceError::Duplicate(card));
+        }
+        if !range.contains(&card) {
+            return Err(missing(card));
+        }
+    }
+    Ok(())
+}
+
+impl Game {
+    /// 根据请求检查控制者的回答，不合法时返回原因
+    pub fn check_choice(&self, choice: &ChoiceReq, res: &ChoiceRes) -> Result<(), ChoiceError> {
+        match (choice, res) {
+            // 取消登场
+            (ChoiceReq::Cost(_), ChoiceRes::None) => Ok(()),
+            (ChoiceReq::Cost(card), ChoiceRes::Cost { hands, real_point }) => {
+                let player_id = self.current_player();
+                check_cards(hands, &self.hand(player_id), ChoiceError::NotInHand)?;
+                let have = self.real_point(player_id);
+                if *real_point > have {
+                    return Err(ChoiceError::NotEnoughRealPoint {
+                        need: *real_point,
+                        have,
+                    });
+                }
+                let slots = self.available_cost_slots(player_id);
+                if hands.len() > slots {
+                    return Err(ChoiceError::CostZoneFull {
+                        slots,
+                        hands: hands.len(),
+                    });
+                }
+                let need = self.get(*card).card_info.cost;
+                let paid = hands.len() + real_point;
+                if paid != need {
+                    return Err(ChoiceError::WrongCost { need, paid });
+                }
+                Ok(())
+            }
+            // 放弃伤害
+            (ChoiceReq::FightDamage, ChoiceRes::None) => Ok(()),
+            (ChoiceReq::FightDamage, ChoiceRes::FightDamageByRealPoint(num)) => {
+                let have = self.current_real_point();
+                // 不使用RealPoint 和回答None一样
+                if *num > have {
+                    return Err(ChoiceError::NotEnoughRealPoint { need: *num, have });
+                }
+                Ok(())
+            }
+            (ChoiceReq::Reuse { player_id, limit }, ChoiceRes::Reuse(cards)) => {
+                let cost = self.cost_zone(*player_id);
+                let need = (*limit).min(cost.len());
+                check_cards(cards, &cost, ChoiceError::NotInCost)?;
+                if cards.len() != need {
+                    return Err(ChoiceError::WrongCount {
+                        need,
+                        got: cards.len(),
+                    });
+                }
+                Ok(())
+            }
+            (ChoiceReq::Mulligan(player_id), ChoiceRes::Mulligan(cards)) => {
+                check_cards(cards, &self.hand(*player_id), ChoiceError::NotInHand)
+            }
+            (ChoiceReq::OrAction(effects), ChoiceRes::OrAction(index)) => {
+                if *index >= effects.len() {
+                    return Err(ChoiceError::NoOption {
+                        index: *index,
+                        count: effects.len(),
+                    });
+                }
+                Ok(())
+            }
+            _ => Err(ChoiceError::WrongAnswer),
+        }
+    }
+
+    /// 多次回答不合法时使用的默认回答
+    pub fn default_choice(&self, choice: &ChoiceReq) -> ChoiceRes {
+        match choice {
+            ChoiceReq::Cost(_) | ChoiceReq::FightDamage => ChoiceRes::None,
+            ChoiceReq::Reuse { player_id, limit } => ChoiceRes::Reuse(
+                self.cost_zone(*player_id)
+                    .into_iter()
+                    .take(*limit)
+                    .collect(),
+            ),
+            ChoiceReq::Mulligan(_) => ChoiceRes::Mulligan(Vec::new()),
+            // 执行第一项
+            ChoiceReq::OrAction(_) => ChoiceRes::OrAction(0),
+        }
+    }
+
+    /// 超时时使用的回答 支付费用时先用手卡再用RealPoint 付不起时取消登场
+    pub fn timeout_choice(&self, choice: &ChoiceReq) -> ChoiceRes {
+        let ChoiceReq::Cost(card) = choice else {
+            return self.default_choice(choice);
+        };
+        let player_id = self.current_player();
+        let need = self.get(*card).card_info.cost;
+        let hands: Vec<EntryId> = self
+            .hand(player_id)
+            .into_iter()
+            .take(need.min(self.available_cost_slots(player_id)))
+            .collect();
+        let real_point = need - hands.len();
+        if real_point > self.real_point(player_id) {
+            return ChoiceRes::None;
+        }
+        ChoiceRes::Cost { hands, real_point }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_cards() {
+        let range = vec![1, 2, 3];
+        assert_eq!(check_cards(&[3, 1], &range, ChoiceError::NotInCost), Ok(()));
+        assert_eq!(
+            check_cards(&[1, 1], &range, ChoiceError::NotInCost),
+            Err(ChoiceError::Duplicate(1))
+        );
+        assert_eq!(
+            check_cards(&[1, 4], &range, ChoiceError::NotInHand),
+            Err(ChoiceError::NotInHand(4))
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::common::EntryId;
+use crate::tr;
+use std::fmt;
+
+/// 选择回答不合法的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChoiceError {
+    // 回答的类型和请求不匹配
+    WrongAnswer,
+    // 卡片不在手卡中
+    NotInHand(EntryId),
+    // 卡片不在费用区中
+    NotInCost(EntryId),
+    // 同一张卡被选择了多次
+    Duplicate(EntryId),
+    // RealPoint不足
+    NotEnoughRealPoint { need: usize, have: usize },
+    // 支付的费用和卡片费用不一致
+    WrongCost { need: usize, paid: usize },
+    // Cost区放不下支付的手卡
+    CostZoneFull { slots: usize, hands: usize },
+    // 选择的卡数不正确
+    WrongCount { need: usize, got: usize },
+    // 选择的效果序号不存在
+    NoOption { index: usize, count: usize },
+}
+
+impl fmt::Display for ChoiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChoiceError::WrongAnswer => write!(f, "{}", tr!("回答的类型和请求不匹配")),
+            ChoiceError::NotInHand(card) => write!(f, "{}", tr!("卡片[{}]不在手卡中", card)),
+            ChoiceError::NotInCost(card) => write!(f, "{}", tr!("卡片[{}]不在费用区中", card)),
+            ChoiceError::Duplicate(card) => write!(f, "{}", tr!("卡片[{}]被重复选择", card)),
+            ChoiceError::NotEnoughRealPoint { need, have } => {
+                write!(f, "{}", tr!("RealPoint不足，需要{}，当前{}", need, have))
+            }
+            ChoiceError::WrongCost { need, paid } => {
+                write!(f, "{}", tr!("支付的费用不正确，需要{}，支付{}", need, paid))
+            }
+            ChoiceError::CostZoneFull { slots, hands } => {
+                write!(
+                    f,
+                    "{}",
+                    tr!("Cost区只剩{}个位置，不能放入{}张手卡", slots, hands)
+                )
+            }
+            ChoiceError::WrongCount { need, got } => {
+                write!(f, "{}", tr!("需要选择{}张卡，选择了{}张", need, got))
+            }
+            ChoiceError::NoOption { index, count } => {
+                write!(f, "{}", tr!("没有选项[{}]，共{}项", index, count))
+            }
+        }
+    }
+}
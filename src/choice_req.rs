@@ -1,6 +1,16 @@
-use crate::common::EntryId;
+use crate::common::{EntryId, PlayerId};
+use crate::effect::DoEffect;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChoiceReq {
     Cost(EntryId),
+    // 直接攻击时选择消耗多少RealPoint造成伤害
+    FightDamage,
+    // 从玩家的费用区选择回收的卡
+    Reuse { player_id: PlayerId, limit: usize },
+    // 起手调度 选择放回卡组的手卡
+    Mulligan(PlayerId),
+    // 任意执行一项效果 选择执行哪一项
+    OrAction(Vec<DoEffect>),
 }
@@ -1,6 +1,7 @@
 use crate::common::EntryId;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChoiceRes {
     None,
     Cost {
@@ -8,4 +9,10 @@ pub enum ChoiceRes {
         real_point: usize,
     },
     FightDamageByRealPoint(usize),
+    // 回收的费用区卡片
+    Reuse(Vec<EntryId>),
+    // 放回卡组重新抽取的手卡
+    Mulligan(Vec<EntryId>),
+    // 执行的效果序号
+    OrAction(usize),
 }
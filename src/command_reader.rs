@@ -1,78 +1,154 @@
+use crate::card_text::{card_details, describe_do_effect};
+use crate::choice_error::ChoiceError;
 use crate::choice_req::ChoiceReq;
 use crate::choice_res::ChoiceRes;
-use crate::common::EntryId;
-use crate::effect::DoEffect::Action;
+use crate::common::{EntryId, PlayerId};
 use crate::game::{Game, GamePhase};
-use crate::player::Player;
-use crate::player_actions::PlayerAction::Pass;
+use crate::i18n::Dbg;
 use crate::player_actions::{PlayerAction, ReadPlayerActions};
+use crate::player_view::CardView;
 use crate::targeting::Targeting;
 use crate::targeting::Targeting::TargetZone;
-use log::{error as log_error, error, info, warn};
-use std::cmp::PartialEq;
-use std::io;
+use crate::time_control::{Deadline, read_stdin_line};
+use crate::tr;
+use log::{error, info, warn};
+use std::path::Path;
+use std::time::Instant;
+
+impl Game {
+    // 读取玩家的一行输入 同一个决定的每一行共用时限 超过时限时记录超时并返回 None
+    fn read_input(&mut self, player_id: PlayerId, deadline: Option<Deadline>) -> Option<String> {
+        // 时间已经用完 不再询问
+        if deadline.is_some_and(|deadline| deadline.expired(Instant::now())) {
+            self.time_out(player_id);
+            return None;
+        }
+        if let Some(deadline) = &deadline {
+            info!(
+                "{}",
+                tr!(
+                    "玩家[{}] 剩余时间{}秒",
+                    player_id,
+                    deadline.remaining(Instant::now()).as_secs_f64().ceil()
+                )
+            );
+        }
+        let input = read_stdin_line(deadline.map(|deadline| deadline.end));
+        if input.is_none() {
+            self.time_out(player_id);
+        }
+        input
+    }
+
+    // 执行玩家的操作 下一个操作重新计时
+    fn accept_action(&mut self, deadline: &mut Option<Deadline>, action: PlayerAction) {
+        self.finish_decision(*deadline);
+        self.deal_player_action(action);
+        *deadline = self.decision_deadline(self.current_player());
+    }
+}
+
+// 每张卡一行
+fn cards_text(cards: &[CardView]) -> String {
+    cards.iter().map(|card| format!("\n  {}", card)).collect()
+}
+
+// 当前玩家查看一张卡的详情 其他玩家的手卡、卡组中的卡和背面朝上的卡不能查看
+fn look_card(game: &Game, entry_id_str: &str) {
+    let Ok(entry_id) = entry_id_str.parse() else {
+        return;
+    };
+    if game
+        .player_view(game.current_player())
+        .find_card(entry_id)
+        .is_none()
+    {
+        error!("{}", tr!("看不到卡片[{}]", entry_id));
+        return;
+    }
+    let card = game.get(entry_id);
+    info!("{}", tr!("卡片详情 {}", card_details(&card.card_info)));
+}
 
 impl ReadPlayerActions for Game {
     fn read_action_main(&mut self) {
+        // 查看信息的命令不重新计时
+        let mut deadline = self.decision_deadline(self.current_player());
         while self.current_phase() == GamePhase::Main || self.current_phase() == GamePhase::Main2 {
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let tokens: Vec<_> = input.trim().split_whitespace().collect();
+            // 等待输入前先读取变化的卡片
+            self.poll_card_watcher();
+            // 超时时结束本阶段
+            let Some(input) = self.read_input(self.current_player(), deadline) else {
+                break;
+            };
+            let tokens: Vec<_> = input.split_whitespace().collect();
             if tokens.is_empty() {
                 continue;
             }
+            // 只显示当前玩家可以看到的信息
+            let view = self.player_view(self.current_player());
+            let me = view.seat(self.current_player());
             match tokens[0] {
                 "help" => {
                     self.help_main();
                 }
+                "view" => {
+                    info!("{}", view);
+                }
                 "hp" => {
-                    info!("hp: {:?}", self.current_hp());
+                    info!("{}", tr!("hp: {}", me.hp));
                 }
                 "real" => {
-                    info!("real: {:?}", self.current_real_point());
+                    info!("{}", tr!("real: {}", me.real_point));
                 }
                 "hand" => {
-                    info!("Hand {:?}", self.current_hand());
+                    let hand = me.hand.as_deref().unwrap_or_default();
+                    info!("{}", tr!("Hand {}", cards_text(hand)));
                 }
                 "cost" => {
-                    info!("Cost {:?}", self.current_cost());
+                    info!("{}", tr!("Cost {}", cards_text(&me.cost)));
                 }
                 "zone" => {
-                    info!("Zone {:?}", self.current_zone());
+                    for zone in &me.zones {
+                        info!("{}", tr!("Zone {}", zone.id));
+                        info!("{}", cards_text(&zone.cards));
+                    }
                 }
                 "desk" => {
-                    info!("Desk left {:?} Cards", self.current_desk_len());
+                    info!("{}", tr!("Desk left {} Cards", me.desk_count));
                 }
                 "grave" => {
-                    info!("Grave {:?}", self.current_grave());
+                    info!("{}", tr!("Grave {}", cards_text(&me.grave)));
                 }
-                "look" => {
-                    if tokens.len() == 2 {
-                        let entry_id_str = tokens[1];
-                        if let Ok(entry_id) = entry_id_str.parse() {
-                            let card = self.get(entry_id);
-                            info!("卡片详情 {:?}", card);
-                        }
-                    }
+                "look" if tokens.len() == 2 => {
+                    look_card(self, tokens[1]);
                 }
                 "set" => {
                     if tokens.len() == 3 {
                         // 创建指令
-                        if let Ok(entry_id) = tokens[1].parse() {
-                            if let Ok(zone_id) = tokens[2].parse() {
-                                let action = PlayerAction::SetCard {
-                                    card_id: entry_id,
-                                    zone_id,
-                                };
-                                // 抛出Action
-                                self.deal_player_action(action);
-                            }
+                        if let Ok(entry_id) = tokens[1].parse()
+                            && let Ok(zone_id) = tokens[2].parse()
+                        {
+                            let action = PlayerAction::SetCard {
+                                card_id: entry_id,
+                                zone_id,
+                            };
+                            // 抛出Action
+                            self.accept_action(&mut deadline, action);
                         }
                     } else {
-                        error!("Wrong number of arguments");
+                        error!("{}", tr!("Wrong number of arguments"));
                     }
                 }
+                "reload" => {
+                    self.reload_cards();
+                }
+                "save" if tokens.len() == 2 => match self.save_to(Path::new(tokens[1])) {
+                    Ok(()) => info!("{}", tr!("游戏已保存到 {}", tokens[1])),
+                    Err(e) => error!("{}", e),
+                },
                 "pass" => {
+                    self.finish_decision(deadline);
                     break;
                 }
                 _ => {}
@@ -81,33 +157,43 @@ impl ReadPlayerActions for Game {
     }
 
     fn read_action_fight(&mut self) {
+        let mut deadline = self.decision_deadline(self.current_player());
         while self.current_phase() == GamePhase::Fight {
             // 提示自己场上可以攻击的卡
-            info!("look card Id 查看详情");
-            info!("可以进行攻击的区域为[{:?}]", self.get_attack_zones());
-            info!("对手场上可以被进攻的区域[{:?}]", self.get_attacked_zones());
+            info!("{}", tr!("look card Id 查看详情"));
+            info!(
+                "{}",
+                tr!("可以进行攻击的区域为[{}]", Dbg(&self.get_attack_zones()))
+            );
+            for player_id in self.opponents_of(self.current_player()) {
+                info!(
+                    "{}",
+                    tr!(
+                        "对手[{}]场上可以被进攻的区域[{}]",
+                        player_id,
+                        Dbg(&self.attacked_zones_of(player_id))
+                    )
+                );
+            }
             info!(
-                "攻击，选取可以攻击的区域进攻某个其他区域\n\
+                "{}",
+                tr!("攻击，选取可以攻击的区域进攻某个其他区域\n\
             attack [zoneId] [zoneId] 自己进攻对手的区域\n\
-            attack [zoneId] 直接攻击对手"
+            attack [zoneId] p[playerId] 直接攻击指定对手\n\
+            attack [zoneId] 直接攻击对手")
             );
-            // 读取数据
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let tokens: Vec<_> = input.trim().split_whitespace().collect();
+            // 读取数据 超时时结束本阶段
+            let Some(input) = self.read_input(self.current_player(), deadline) else {
+                break;
+            };
+            let tokens: Vec<_> = input.split_whitespace().collect();
             if tokens.is_empty() {
                 continue;
             }
             // 这里只生成两种的处理
             match tokens[0] {
-                "look" => {
-                    if tokens.len() == 2 {
-                        let entry_id_str = tokens[1];
-                        if let Ok(entry_id) = entry_id_str.parse() {
-                            let card = self.get(entry_id);
-                            info!("卡片详情 {:?}", card);
-                        }
-                    }
+                "look" if tokens.len() == 2 => {
+                    look_card(self, tokens[1]);
                 }
                 "attack" => {
                     // 这里处理攻击的对象问题
@@ -115,27 +201,48 @@ impl ReadPlayerActions for Game {
                         // 取第二个和三个
                         let my_zone = tokens[1];
                         let opponent_zone = tokens[2];
-                        if let Ok(my_zone_id) = my_zone.parse() {
-                            if let Ok(opponent_zone_id) = opponent_zone.parse() {
-                                self.deal_player_action(PlayerAction::AttackCard {
+                        if let Ok(my_zone_id) = my_zone.parse()
+                            && let Some(player_id) = opponent_zone.strip_prefix('p')
+                        {
+                            if let Ok(player_id) = player_id.parse() {
+                                self.accept_action(
+                                    &mut deadline,
+                                    PlayerAction::AttackCard {
+                                        source: TargetZone(my_zone_id),
+                                        target: Targeting::TargetPlayer(player_id),
+                                    },
+                                );
+                            } else {
+                                error!("{}", tr!("玩家id解析错误"));
+                            }
+                        } else if let Ok(my_zone_id) = my_zone.parse()
+                            && let Ok(opponent_zone_id) = opponent_zone.parse()
+                        {
+                            self.accept_action(
+                                &mut deadline,
+                                PlayerAction::AttackCard {
                                     source: TargetZone(my_zone_id),
                                     target: TargetZone(opponent_zone_id),
-                                });
-                            }
+                                },
+                            );
                         }
                     }
                     if tokens.len() == 2 {
                         // 值取源
                         let my_zone = tokens[1];
                         if let Ok(my_zone_id) = my_zone.parse() {
-                            self.deal_player_action(PlayerAction::AttackCard {
-                                source: TargetZone(my_zone_id),
-                                target: Targeting::TargetPlayerOpponent,
-                            });
+                            self.accept_action(
+                                &mut deadline,
+                                PlayerAction::AttackCard {
+                                    source: TargetZone(my_zone_id),
+                                    target: Targeting::TargetPlayerOpponent,
+                                },
+                            );
                         }
                     }
                 }
                 "pass" => {
+                    self.finish_decision(deadline);
                     break;
                 }
                 _ => {}
@@ -144,17 +251,21 @@ impl ReadPlayerActions for Game {
     }
 
     // 选择是否使用伤害
-    fn read_fight_damage(&mut self) -> ChoiceRes {
+    fn read_fight_damage(&mut self, deadline: Option<Deadline>) -> ChoiceRes {
         loop {
             info!(
-                "直接攻击玩家。是否消耗RealPoint对对手造成伤害,当前RealPoint[{:?}]",
-                self.current_real_point()
+                "{}",
+                tr!(
+                    "直接攻击玩家。是否消耗RealPoint对对手造成伤害,当前RealPoint[{}]",
+                    Dbg(&self.current_real_point())
+                )
             );
-            info!("造成伤害[num]。放弃伤害，获得RealPoint: pass|0");
+            info!("{}", tr!("造成伤害[num]。放弃伤害，获得RealPoint: pass|0"));
             // 读取数据
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let tokens: Vec<_> = input.trim().split_whitespace().collect();
+            let Some(input) = self.read_input(self.current_player(), deadline) else {
+                break;
+            };
+            let tokens: Vec<_> = input.split_whitespace().collect();
             if tokens.is_empty() {
                 continue;
             }
@@ -169,56 +280,61 @@ impl ReadPlayerActions for Game {
                     }
                     // 这里进行处理
                     if num > self.current_real_point() {
-                        error!("不能申请大于当前拥有的RealPoint");
+                        error!("{}", tr!("不能申请大于当前拥有的RealPoint"));
                         continue;
                     }
                     return ChoiceRes::FightDamageByRealPoint(num);
                 } else {
-                    warn!("类型解析错误")
+                    warn!("{}", tr!("类型解析错误"))
                 }
             }
         }
         ChoiceRes::None
     }
 
-    fn read_choice(&mut self, choice: ChoiceReq) -> ChoiceRes {
+    fn read_choice(
+        &mut self,
+        player_id: PlayerId,
+        choice: ChoiceReq,
+        deadline: Option<Deadline>,
+    ) -> ChoiceRes {
         match choice {
+            ChoiceReq::FightDamage => self.read_fight_damage(deadline),
             ChoiceReq::Cost(card) => {
-                // 检查费用是否足够
-                if !self.check_cost(card) {
-                    error!("无法支付费用,怎么返回原处");
-                    self.set_rollback(card);
-                    return ChoiceRes::None;
-                }
                 loop {
                     // 显示可以使用的数据
-                    info!("Cost {:?}", self.current_cost());
-                    info!("Hand {:?}", self.current_hand());
-                    info!("Real Point:{:?}", self.current_real_point());
-                    info!("Zone {:?}", self.current_zone());
+                    info!("{}", tr!("Cost {}", Dbg(&self.current_cost())));
+                    info!("{}", tr!("Hand {}", Dbg(&self.current_hand())));
+                    info!("{}", tr!("Real Point:{}", Dbg(&self.current_real_point())));
+                    info!("{}", tr!("Zone {}", Dbg(&self.current_zone())));
                     info!(
-                        "登场[{:?}]支付的费用为 {:?}",
-                        self.get(card).card_info.clone().name,
-                        self.get(card).card_info.clone().cost
+                        "{}",
+                        tr!(
+                            "登场[{}]支付的费用为 {}",
+                            Dbg(&self.get(card).card_info.clone().name),
+                            Dbg(&self.get(card).card_info.clone().cost)
+                        )
                     );
                     info!(
-                        "请选择 你要的支付费用的卡\
+                        "{}",
+                        tr!("请选择 你要的支付费用的卡\
                         \n。[id1,id2,.. realPoint] 任意手卡id（逗号隔开）和realPoint的组合\
-                        \n取消操作 cancel"
+                        \n取消操作 cancel")
                     );
                     //FIXME ： 这里的阅读循环怎么优化重构？
-                    let mut input = String::new();
-                    io::stdin().read_line(&mut input).unwrap();
-                    let tokens: Vec<_> = input.trim().split_whitespace().collect();
+                    let Some(input) = self.read_input(player_id, deadline) else {
+                        return ChoiceRes::None;
+                    };
+                    let tokens: Vec<_> = input.split_whitespace().collect();
                     if tokens.is_empty() {
                         continue;
                     }
                     // 纯支付点数
                     if tokens.len() == 1 {
-                        let point = tokens[1];
+                        let point = tokens[0];
                         if let Ok(point) = point.parse() {
                             if point != self.get(card).card_info.clone().cost {
-                                error!("所需的费用不正确");
+                                error!("{}", tr!("所需的费用不正确"));
                             } else {
                                 return ChoiceRes::Cost {
                                     hands: Vec::new(),
@@ -229,20 +345,20 @@ impl ReadPlayerActions for Game {
                     }
 
                     if tokens.len() == 1 && tokens[0] == "cancel" {
-                        info!("取消操作");
-                        self.set_rollback(card);
+                        info!("{}", tr!("取消操作"));
                         return ChoiceRes::None;
                     }
                     if tokens.len() == 2 {
                         let hands_str = tokens[0];
-                        let hands: Vec<EntryId> = hands_str
+                        let Ok(hands) = hands_str
                             .trim()
                             .split(",")
-                            .map(|x| {
-                                let Ok(r) = x.parse() else { todo!() };
-                                r
-                            })
-                            .collect();
+                            .map(|x| x.parse())
+                            .collect::<Result<Vec<EntryId>, _>>()
+                        else {
+                            error!("{}", tr!("类型解析错误"));
+                            continue;
+                        };
                         let point = tokens[1];
                         return if let Ok(point) = point.parse() {
                             ChoiceRes::Cost {
@@ -258,43 +374,104 @@ impl ReadPlayerActions for Game {
                     }
                 }
             }
+            ChoiceReq::Reuse {
+                player_id: target_id,
+                limit,
+            } => ChoiceRes::Reuse(self.read_reuse_choice(player_id, target_id, limit, deadline)),
+            ChoiceReq::Mulligan(player_id) => loop {
+                let hand = self.hand(player_id);
+                info!(
+                    "{}",
+                    tr!("玩家[{}] 起手调度 Hand {}", Dbg(&player_id), Dbg(&hand))
+                );
+                info!(
+                    "{}",
+                    tr!("选择放回卡组重新抽取的手卡\
+                    \n[id1,id2,..] 任意手卡id（逗号隔开）\
+                    \n保留全部手卡 keep")
+                );
+                let Some(input) = self.read_input(player_id, deadline) else {
+                    return ChoiceRes::None;
+                };
+                let tokens: Vec<_> = input.split_whitespace().collect();
+                if tokens.len() != 1 {
+                    continue;
+                }
+                if tokens[0] == "keep" {
+                    return ChoiceRes::Mulligan(Vec::new());
+                }
+                let cards: Result<Vec<EntryId>, _> =
+                    tokens[0].split(",").map(|x| x.parse()).collect();
+                match cards {
+                    Ok(cards) if cards.iter().all(|card| hand.contains(card)) => {
+                        return ChoiceRes::Mulligan(cards);
+                    }
+                    _ => error!("{}", tr!("只能选择自己的手卡")),
+                }
+            },
+            ChoiceReq::OrAction(effects) => loop {
+                for (index, effect) in effects.iter().enumerate() {
+                    info!("{}", tr!("选项[{}] {}", index, describe_do_effect(effect)));
+                }
+                info!("{}", tr!("选择执行的效果 [序号]"));
+                let Some(input) = self.read_input(player_id, deadline) else {
+                    return ChoiceRes::None;
+                };
+                match input.trim().parse() {
+                    Ok(index) => return ChoiceRes::OrAction(index),
+                    Err(_) => error!("{}", tr!("类型解析错误")),
+                }
+            },
         }
     }
 
+    fn read_choice_error(&mut self, _choice: &ChoiceReq, choice_error: &ChoiceError) {
+        error!("{}", tr!("选择不合法: {}，请重新选择", choice_error));
+    }
+
     // 选取要进行回收的卡
-    fn read_reuse_choice(&mut self, targeting: Targeting, limit: usize) -> Vec<EntryId> {
-        let amin_id = if let Targeting::TargetPlayerSelf = targeting {
-            self.current_player()
-        } else {
-            self.next_player_id()
-        };
-        info!("当前选择用户{:?}", amin_id);
-        let costs = if amin_id == self.current_player() {
-            self.current_cost()
-        } else {
-            self.next_cost()
-        };
-        info!("可以选择的数据为{:?}。从中选取{:?}", costs.clone(), limit);
-        info!("[id1,id2]使用逗号隔开");
+    fn read_reuse_choice(
+        &mut self,
+        chooser: PlayerId,
+        player_id: PlayerId,
+        limit: usize,
+        deadline: Option<Deadline>,
+    ) -> Vec<EntryId> {
+        info!("{}", tr!("当前选择用户{}", Dbg(&player_id)));
+        let costs = self.cost_zone(player_id);
+        info!(
+            "{}",
+            tr!(
+                "可以选择的数据为{}。从中选取{}",
+                Dbg(&costs.clone()),
+                Dbg(&limit)
+            )
+        );
+        info!("{}", tr!("[id1,id2]使用逗号隔开"));
         loop {
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let tokens: Vec<_> = input.trim().split_whitespace().collect();
+            let Some(input) = self.read_input(chooser, deadline) else {
+                return Vec::new();
+            };
+            let tokens: Vec<_> = input.split_whitespace().collect();
             if tokens.is_empty() {
                 continue;
             }
             if tokens.len() == 1 {
                 let hands_str = tokens[0];
-                let hands: Vec<EntryId> = hands_str
+                let Ok(hands) = hands_str
                     .trim()
                     .split(",")
-                    .map(|x| {
-                        let Ok(r) = x.parse() else { todo!() };
-                        r
-                    })
+                    .map(|x| x.parse())
+                    .collect::<Result<Vec<EntryId>, _>>()
+                else {
+                    error!("{}", tr!("类型解析错误"));
+                    continue;
+                };
+                let hands: Vec<EntryId> = hands
+                    .into_iter()
                     .filter(|x| {
                         if !costs.contains(x) {
-                            warn!("{:?} 不在可选列表内", costs)
+                            warn!("{}", tr!("{} 不在可选列表内", Dbg(&costs)))
                         }
                         costs.contains(x)
                     })
@@ -303,23 +480,24 @@ impl ReadPlayerActions for Game {
                     if hands.len() == costs.len() {
                         return hands;
                     } else {
-                        error!("选择卡数据要进可能的接近要求");
+                        error!("{}", tr!("选择卡数据要进可能的接近要求"));
                     }
                 } else if hands.len() > limit {
-                    error!("选择卡数超过要求");
+                    error!("{}", tr!("选择卡数超过要求"));
                 } else {
                     return hands;
                 }
             }
         }
-
-        Vec::new()
     }
 
     fn help_main(&self) {
         info!(
-            "Player {:?} 请操作:\n\
+            "{}",
+            tr!(
+                "Player {} 请操作:\n\
             help    帮助\n\
+            view    查看对局\n\
             hp      血量\n\
             real    真实点数\n\
             hand    查看手牌\n\
@@ -328,8 +506,11 @@ impl ReadPlayerActions for Game {
             grave   查看墓地区\n \
             desk    查看卡组查看卡组剩余\n\
             set [entryId] [zoneId]\n\
+            reload  重新读取卡片脚本\n\
+            save    [file] 保存游戏\n\
             ",
-            self.current_player()
+                Dbg(&self.current_player())
+            )
         );
     }
 }
@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 // 实体ID
 pub type EntryId = usize;
 pub type PlayerId = usize;
 // 卡片定义id
 pub type CardInfoId = String;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct IdGenerator(usize);
 
 impl IdGenerator {
@@ -0,0 +1,177 @@
+use crate::common::{CardInfoId, PlayerId};
+use crate::player::PlayerDesk;
+use crate::ruleset::Ruleset;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 卡组不符合规则的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeckViolation {
+    // 卡数不足
+    TooFew {
+        size: usize,
+        min: usize,
+    },
+    // 卡数过多
+    TooMany {
+        size: usize,
+        max: usize,
+    },
+    // 同名卡超过张数限制
+    TooManyCopies {
+        card_id: CardInfoId,
+        count: usize,
+        max: usize,
+    },
+    // 禁止使用的卡片
+    Banned(CardInfoId),
+    // 不在可以使用的卡片中
+    NotInPool(CardInfoId),
+}
+
+impl fmt::Display for DeckViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckViolation::TooFew { size, min } => {
+                write!(f, "卡组只有{}张卡，至少需要{}张", size, min)
+            }
+            DeckViolation::TooMany { size, max } => {
+                write!(f, "卡组有{}张卡，最多只能有{}张", size, max)
+            }
+            DeckViolation::TooManyCopies {
+                card_id,
+                count,
+                max,
+            } => write!(
+                f,
+                "卡片[{}]放入了{}张，最多只能放入{}张",
+                card_id, count, max
+            ),
+            DeckViolation::Banned(card_id) => write!(f, "卡片[{}]禁止使用", card_id),
+            DeckViolation::NotInPool(card_id) => {
+                write!(f, "卡片[{}]不能在这个规则中使用", card_id)
+            }
+        }
+    }
+}
+
+/// 开始游戏时玩家的卡组不符合规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckError {
+    pub player_id: PlayerId,
+    pub violations: Vec<DeckViolation>,
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "玩家[{}]的卡组不符合规则", self.player_id)?;
+        for violation in &self.violations {
+            write!(f, "\n  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+// 卡片id是否符合列表中的一项 `S000-*` 匹配前缀
+fn listed(list: &[CardInfoId], card_id: &str) -> bool {
+    list.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => card_id.starts_with(prefix),
+        None => pattern == card_id,
+    })
+}
+
+/// 按规则检查卡组 返回全部违反的规则
+pub fn check_deck(desk: &PlayerDesk, ruleset: &Ruleset) -> Vec<DeckViolation> {
+    let mut violations = Vec::new();
+    let size = desk.0.len();
+    if size < ruleset.min_deck_size {
+        violations.push(DeckViolation::TooFew {
+            size,
+            min: ruleset.min_deck_size,
+        });
+    }
+    if ruleset.max_deck_size > 0 && size > ruleset.max_deck_size {
+        violations.push(DeckViolation::TooMany {
+            size,
+            max: ruleset.max_deck_size,
+        });
+    }
+    // 按卡片id排序 报告顺序固定
+    let mut counts: BTreeMap<&CardInfoId, usize> = BTreeMap::new();
+    for card_id in &desk.0 {
+        *counts.entry(card_id).or_default() += 1;
+    }
+    for (card_id, count) in counts {
+        if listed(&ruleset.banned, card_id) {
+            violations.push(DeckViolation::Banned(card_id.clone()));
+            continue;
+        }
+        if !ruleset.card_pool.is_empty() && !listed(&ruleset.card_pool, card_id) {
+            violations.push(DeckViolation::NotInPool(card_id.clone()));
+            continue;
+        }
+        let max = if listed(&ruleset.limited, card_id) {
+            1
+        } else {
+            ruleset.max_copies
+        };
+        if max > 0 && count > max {
+            violations.push(DeckViolation::TooManyCopies {
+                card_id: card_id.clone(),
+                count,
+                max,
+            });
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desk(cards: &[(&str, usize)]) -> PlayerDesk {
+        PlayerDesk(
+            cards
+                .iter()
+                .flat_map(|(id, count)| std::iter::repeat_n(id.to_string(), *count))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_default_rules_accept_any_deck() {
+        assert!(check_deck(&desk(&[("A", 40)]), &Ruleset::default()).is_empty());
+    }
+
+    #[test]
+    fn test_deck_violations() {
+        let ruleset = Ruleset::parse(
+            "constructed".to_string(),
+            "min_deck_size = 10\nmax_copies = 3\nbanned = B\nlimited = C\ncard_pool = S1-*, B, C",
+        )
+        .unwrap();
+        let violations = check_deck(
+            &desk(&[("S1-A", 4), ("B", 1), ("C", 2), ("S2-A", 1)]),
+            &ruleset,
+        );
+        assert_eq!(
+            violations,
+            vec![
+                DeckViolation::TooFew { size: 8, min: 10 },
+                DeckViolation::Banned("B".to_string()),
+                DeckViolation::TooManyCopies {
+                    card_id: "C".to_string(),
+                    count: 2,
+                    max: 1
+                },
+                DeckViolation::TooManyCopies {
+                    card_id: "S1-A".to_string(),
+                    count: 4,
+                    max: 3
+                },
+                DeckViolation::NotInPool("S2-A".to_string()),
+            ]
+        );
+    }
+}
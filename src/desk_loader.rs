@@ -1,25 +1,306 @@
-use crate::player::PlayerDesk;
+use crate::load_error::{LoadError, LoadReport};
+use crate::lua_api::LuaApi;
+use crate::player::{Deck, PlayerDesk};
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 
-pub fn load_desks() -> HashMap<String, PlayerDesk> {
+// include 最多嵌套的层数
+const MAX_INCLUDE_DEPTH: usize = 8;
+// 一行最多的卡片数量
+const MAX_LINE_COUNT: usize = 100;
+// 一个卡组最多的卡片数 防止重复 include 使卡组过大
+const MAX_DECK_CARDS: usize = 1000;
+
+/// 读取 desks 目录下的卡组 引用了不存在卡片的卡组不能使用
+pub fn load_desks(
+    api: &LuaApi,
+    report: &mut LoadReport,
+) -> Result<HashMap<String, Deck>, LoadError> {
+    let dir = Path::new("desks");
     let mut res = HashMap::new();
-    for file in fs::read_dir("desks").unwrap() {
-        if let Ok(file) = file {
-            if file.path().is_file() {
-                if let Some(file_name) = file.file_name().to_str() {
-                    let code = fs::read_to_string(file.path()).unwrap();
-                    let codes: Vec<String> = code
-                        .trim()
-                        .split("\n")
-                        .map(|x| x.trim())
-                        .filter(|part| !part.is_empty()) // 过滤空字符串
-                        .map(|s| s.to_string()) // 转换为 String
-                        .collect();
-                    res.insert(file_name.to_string(), PlayerDesk(codes));
+    let files = match fs::read_dir(dir) {
+        Ok(files) => files,
+        Err(e) => {
+            report.fail(LoadError::io(dir, e))?;
+            return Ok(res);
+        }
+    };
+    // include 的卡组从同一个目录读取
+    let read = |name: &str| {
+        let path = dir.join(name);
+        fs::read_to_string(&path).map_err(|e| LoadError::io(&path, e))
+    };
+    for file in files.flatten() {
+        let path = file.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(file_name) = file.file_name().to_str().map(|s| s.to_string()) else {
+            continue;
+        };
+        let result = read(&file_name).and_then(|code| parse_deck(&file_name, &code, api, &read));
+        match result {
+            Ok(deck) => {
+                res.insert(file_name, deck);
+                report.loaded.push(path);
+            }
+            Err(load_error) => report.fail(load_error)?,
+        }
+    }
+    Ok(res)
+}
+
+/// 解析卡组文件 `read` 按文件名读取 include 的卡组
+///
+/// ```text
+/// # 注释
+/// name = 卡组名称
+/// author = 作者
+/// [main]
+/// 3x CARD-ID
+/// CARD-ID
+/// [side]
+/// 2x CARD-ID
+/// include 其他卡组
+/// ```
+///
+/// 没有分区时全部为主卡组 兼容每行一个卡片id的旧格式
+pub fn parse_deck<F>(name: &str, code: &str, api: &LuaApi, read: &F) -> Result<Deck, LoadError>
+where
+    F: Fn(&str) -> Result<String, LoadError>,
+{
+    let mut deck = Deck {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    parse_into(
+        &mut deck,
+        name,
+        code,
+        api,
+        read,
+        &mut vec![name.to_string()],
+    )?;
+    Ok(deck)
+}
+
+// 解析一个文件的卡片 include 的卡组按分区合并 名称和作者以最外层为准
+fn parse_into<F>(
+    deck: &mut Deck,
+    name: &str,
+    code: &str,
+    api: &LuaApi,
+    read: &F,
+    including: &mut Vec<String>,
+) -> Result<(), LoadError>
+where
+    F: Fn(&str) -> Result<String, LoadError>,
+{
+    let outermost = including.len() == 1;
+    let mut side = false;
+    for (index, line) in code.lines().enumerate() {
+        let syntax_error = |message: String| LoadError::DeckSyntax {
+            deck: name.to_string(),
+            line: index + 1,
+            message,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line {
+            "[main]" => side = false,
+            "[side]" => side = true,
+            _ if line.starts_with('[') => {
+                return Err(syntax_error(format!("未知的分区 {}", line)));
+            }
+            _ => {
+                if let Some((key, value)) = line.split_once('=') {
+                    let value = value.trim().to_string();
+                    match key.trim() {
+                        "name" if outermost => deck.name = value,
+                        "author" if outermost => deck.author = Some(value),
+                        "name" | "author" => {}
+                        key => return Err(syntax_error(format!("未知的卡组信息 {}", key))),
+                    }
+                    continue;
+                }
+                if let Some(include) = line.strip_prefix("include ") {
+                    let include = include_name(include).map_err(syntax_error)?;
+                    if including.contains(&include) {
+                        return Err(syntax_error(format!("卡组[{}]循环引用", include)));
+                    }
+                    if including.len() > MAX_INCLUDE_DEPTH {
+                        return Err(syntax_error(format!(
+                            "include 嵌套超过{}层",
+                            MAX_INCLUDE_DEPTH
+                        )));
+                    }
+                    let mut included = Deck::default();
+                    let code = read(&include)?;
+                    including.push(include.clone());
+                    parse_into(&mut included, &include, &code, api, read, including)?;
+                    including.pop();
+                    deck.main.0.extend(included.main.0);
+                    deck.side.0.extend(included.side.0);
+                    check_size(deck).map_err(syntax_error)?;
+                    continue;
+                }
+                let (count, card_id) = parse_count(line).map_err(syntax_error)?;
+                if !api.cards.contains_key(card_id) {
+                    return Err(LoadError::UnknownCard {
+                        deck: name.to_string(),
+                        line: index + 1,
+                        card_id: card_id.to_string(),
+                    });
                 }
+                let section: &mut PlayerDesk = if side { &mut deck.side } else { &mut deck.main };
+                section
+                    .0
+                    .extend(std::iter::repeat_n(card_id.to_string(), count));
+                check_size(deck).map_err(syntax_error)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// `3x CARD-ID` 或 `CARD-ID`
+fn parse_count(line: &str) -> Result<(usize, &str), String> {
+    let Some((count, card_id)) = line.split_once(char::is_whitespace) else {
+        return Ok((1, line));
+    };
+    let count: usize = count
+        .strip_suffix('x')
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| format!("数量格式错误 {}", line))?;
+    if count == 0 {
+        return Err(format!("数量不能为0 {}", line));
+    }
+    if count > MAX_LINE_COUNT {
+        return Err(format!("数量不能超过{} {}", MAX_LINE_COUNT, line));
+    }
+    Ok((count, card_id.trim()))
+}
+
+// include 只能引用同一目录下的卡组文件 去掉空白后的名称用于判断循环引用
+fn include_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") || name == "." {
+        return Err(format!("不能 include 其他目录的文件 {}", name));
+    }
+    Ok(name.to_string())
+}
+
+// 卡组的总卡数不能超过上限
+fn check_size(deck: &Deck) -> Result<(), String> {
+    if deck.main.0.len() + deck.side.0.len() > MAX_DECK_CARDS {
+        return Err(format!("卡组超过{}张", MAX_DECK_CARDS));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardInfoBuilder;
+
+    fn test_api() -> LuaApi {
+        let mut api = LuaApi::new();
+        for id in ["A", "B"] {
+            api.cards
+                .insert(id.to_string(), CardInfoBuilder::new(id.to_string()).build());
+        }
+        api
+    }
+
+    fn no_include(name: &str) -> Result<String, LoadError> {
+        Err(LoadError::Io {
+            path: name.into(),
+            message: "not found".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_old_format() {
+        let deck = parse_deck("d", "A\n\nA\nB\n", &test_api(), &no_include).unwrap();
+        assert_eq!(deck.name, "d");
+        assert_eq!(deck.main.0, vec!["A", "A", "B"]);
+        assert!(deck.side.0.is_empty());
+    }
+
+    #[test]
+    fn test_sections_and_metadata() {
+        let code = "# 测试\nname = 测试卡组\nauthor = tester\n[main]\n3x A\nB\n[side]\n2x B\n";
+        let deck = parse_deck("d", code, &test_api(), &no_include).unwrap();
+        assert_eq!(deck.name, "测试卡组");
+        assert_eq!(deck.author.as_deref(), Some("tester"));
+        assert_eq!(deck.main.0, vec!["A", "A", "A", "B"]);
+        assert_eq!(deck.side.0, vec!["B", "B"]);
+    }
+
+    #[test]
+    fn test_include() {
+        let read = |name: &str| match name {
+            "base" => Ok("name = base\n2x A\n[side]\nB".to_string()),
+            "loop" => Ok("include d".to_string()),
+            _ => no_include(name),
+        };
+        let deck = parse_deck("d", "B\ninclude base\n", &test_api(), &read).unwrap();
+        assert_eq!(deck.name, "d");
+        assert_eq!(deck.main.0, vec!["B", "A", "A"]);
+        assert_eq!(deck.side.0, vec!["B"]);
+        assert!(matches!(
+            parse_deck("d", "include loop", &test_api(), &read),
+            Err(LoadError::DeckSyntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_include_limits() {
+        // 每一层引用下一层 超过嵌套层数
+        let deep = |name: &str| {
+            let depth: usize = name.trim_start_matches('n').parse().unwrap();
+            Ok(format!("A\ninclude n{}", depth + 1))
+        };
+        assert!(matches!(
+            parse_deck("n0", "include n1", &test_api(), &deep),
+            Err(LoadError::DeckSyntax { .. })
+        ));
+        // 重复 include 使卡组过大
+        let wide = |name: &str| match name {
+            "base" => Ok("100x A".to_string()),
+            _ => no_include(name),
+        };
+        let code = "include base\n".repeat(11);
+        assert!(matches!(
+            parse_deck("d", &code, &test_api(), &wide),
+            Err(LoadError::DeckSyntax { line: 11, .. })
+        ));
+        for code in ["include ../d", "include a/b", "include ..", "include c:\\d"] {
+            assert!(matches!(
+                parse_deck("d", code, &test_api(), &no_include),
+                Err(LoadError::DeckSyntax { line: 1, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_deck_errors() {
+        assert_eq!(
+            parse_deck("d", "A\n\nB\nC\n", &test_api(), &no_include).unwrap_err(),
+            LoadError::UnknownCard {
+                deck: "d".to_string(),
+                line: 4,
+                card_id: "C".to_string(),
             }
+        );
+        for code in ["0x A", "101x A", "xx A", "[extra]", "title = x"] {
+            assert!(matches!(
+                parse_deck("d", code, &test_api(), &no_include),
+                Err(LoadError::DeckSyntax { line: 1, .. })
+            ));
         }
     }
-    res
 }
@@ -1,18 +1,20 @@
 use crate::common::EntryId;
+use crate::game::GamePhase;
 use crate::targeting::Targeting;
 use mlua::{UserData, UserDataMethods};
+use serde::{Deserialize, Serialize};
 
-/// TODO 这里讨论效果的详情
-///
-/// 1. id
-/// 2. 发动的条件
-/// 3. 发动的窗口 window 和游戏的阶段有关
-/// 4. 成功后的动作 Option_Action {target目标（选取范围）}
-/// 5. 频率限制
-///
+// TODO 这里讨论效果的详情
+//
+// 1. id
+// 2. 发动的条件
+// 3. 发动的窗口 window 和游戏的阶段有关
+// 4. 成功后的动作 Option_Action {target目标（选取范围）}
+// 5. 频率限制
+//
 
 /// 效果类
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Effect {
     id: String,
     pub windows_tag: WindowsTag,
@@ -29,6 +31,12 @@ pub struct EffectBuilder {
     do_effect: DoEffect,
 }
 
+impl Effect {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
 impl EffectBuilder {
     pub fn new(id: String) -> EffectBuilder {
         Self {
@@ -53,34 +61,40 @@ impl UserData for EffectBuilder {
         // 这要添加方法
 
         // 添加操作窗口标签
-        methods.add_method_mut("window", |lua, this, tag: String| {
-            match tag.as_str() {
-                "self_start" => this.windows_tag = WindowsTag::OnSelfStart,
-                "opponent_start" => this.windows_tag = WindowsTag::OnOpponentStart,
-                "start" => this.windows_tag = WindowsTag::OnStart,
-                "cost" => this.windows_tag = WindowsTag::OnCost,
-                "set" => this.windows_tag = WindowsTag::OnSet,
-                "main" => this.windows_tag = WindowsTag::DuringMain,
-                "attack" => this.windows_tag = WindowsTag::OnAttack,
-                _ => {}
-            }
+        methods.add_method_mut("window", |_lua, this, tag: String| {
+            this.windows_tag = WindowsTag::from_name(&tag)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("未知的窗口 {}", tag)))?;
             Ok(())
         });
 
         methods.add_method_mut("draw", |_, this, num: usize| {
-            let effect = DoEffect::Action {
-                source: Default::default(),
-                targeting: Targeting::TargetPlayerSelf,
-                action: Action::Draw(num),
-            };
-            this.do_effect = effect;
+            this.do_effect = DoEffect::action(Action::Draw(num));
+            Ok(())
+        });
+
+        methods.add_method_mut("damage", |_, this, num: usize| {
+            this.do_effect = DoEffect::action(Action::Damage(num));
+            Ok(())
+        });
+
+        methods.add_method_mut("skip_phase", |_, this, name: String| {
+            let phase = GamePhase::from_name(&name)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("未知的阶段 {}", name)))?;
+            this.do_effect = DoEffect::action(Action::SkipPhase(phase));
+            Ok(())
+        });
+
+        methods.add_method_mut("add_phase", |_, this, name: String| {
+            let phase = GamePhase::from_name(&name)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("未知的阶段 {}", name)))?;
+            this.do_effect = DoEffect::action(Action::AddPhase(phase));
             Ok(())
         });
     }
 }
 
 /// 窗口标签
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WindowsTag {
     #[default]
     None,
@@ -98,9 +112,30 @@ pub enum WindowsTag {
     DuringMain,
     // 攻击时
     OnAttack,
+    // 进入某个阶段时
+    OnPhase(GamePhase),
 }
 
-#[derive(Debug, Clone, Default)]
+impl WindowsTag {
+    // 脚本和数据卡片中使用的窗口名称 `phase:draw` 为进入指定阶段时
+    pub fn from_name(name: &str) -> Option<WindowsTag> {
+        match name {
+            "self_start" => Some(WindowsTag::OnSelfStart),
+            "opponent_start" => Some(WindowsTag::OnOpponentStart),
+            "start" => Some(WindowsTag::OnStart),
+            "cost" => Some(WindowsTag::OnCost),
+            "set" => Some(WindowsTag::OnSet),
+            "main" => Some(WindowsTag::DuringMain),
+            "attack" => Some(WindowsTag::OnAttack),
+            _ => name
+                .strip_prefix("phase:")
+                .and_then(GamePhase::from_name)
+                .map(WindowsTag::OnPhase),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DoEffect {
     // 操作
     #[default]
@@ -110,21 +145,86 @@ pub enum DoEffect {
         targeting: Targeting,
         action: Action,
     },
-    // n选1
+    // 依次全部执行
     AndAction(Vec<DoEffect>),
     // 任意操作
     OrAction(Vec<DoEffect>),
 }
 
+impl DoEffect {
+    // 以动作的默认目标创建效果 来源在发动时设置
+    pub fn action(action: Action) -> DoEffect {
+        let targeting = match action {
+            Action::Draw(_) => Targeting::TargetPlayerSelf,
+            Action::Damage(_) => Targeting::TargetPlayerOpponent,
+            _ => Targeting::None,
+        };
+        DoEffect::Action {
+            source: Default::default(),
+            targeting,
+            action,
+        }
+    }
+
+    // 效果是否没有任何动作
+    pub fn is_empty(&self) -> bool {
+        match self {
+            DoEffect::None => true,
+            DoEffect::Action { action, .. } => matches!(action, Action::None),
+            DoEffect::AndAction(effects) | DoEffect::OrAction(effects) => {
+                effects.iter().all(|effect| effect.is_empty())
+            }
+        }
+    }
+
+    // 效果的来源 组合效果使用第一项的来源
+    pub fn source(&self) -> Targeting {
+        match self {
+            DoEffect::None => Targeting::None,
+            DoEffect::Action { source, .. } => source.clone(),
+            DoEffect::AndAction(effects) | DoEffect::OrAction(effects) => effects
+                .first()
+                .map(|effect| effect.source())
+                .unwrap_or_default(),
+        }
+    }
+
+    // 设置效果的来源 卡片发动的效果以卡片的控制者结算
+    pub fn with_source(self, source: Targeting) -> DoEffect {
+        match self {
+            DoEffect::None => DoEffect::None,
+            DoEffect::Action {
+                targeting, action, ..
+            } => DoEffect::Action {
+                source,
+                targeting,
+                action,
+            },
+            DoEffect::AndAction(effects) => DoEffect::AndAction(
+                effects
+                    .into_iter()
+                    .map(|effect| effect.with_source(source.clone()))
+                    .collect(),
+            ),
+            DoEffect::OrAction(effects) => DoEffect::OrAction(
+                effects
+                    .into_iter()
+                    .map(|effect| effect.with_source(source.clone()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
 /// 条件
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Condition {
     #[default]
     None,
 }
 
 /// 操作效果
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
     // 没有任何操作
     #[default]
@@ -154,4 +254,8 @@ pub enum Action {
     AskingReuse(usize),
     // 卡片回收
     Reuse(Vec<EntryId>),
+    // 跳过本回合的阶段
+    SkipPhase(GamePhase),
+    // 在当前阶段之后追加阶段
+    AddPhase(GamePhase),
 }
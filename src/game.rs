@@ -1,31 +1,51 @@
-use crate::card::Card;
+use crate::card::{Card, CardDefs, CardInfo};
+use crate::card_reload::{CardReload, CardSource, CardWatcher, diff_card_defs, reload_card_defs};
+use crate::choice_check::check_cards;
+use crate::choice_error::ChoiceError;
 use crate::choice_req::ChoiceReq;
 use crate::choice_res::ChoiceRes;
-use crate::common::{EntryId, IdGenerator, PlayerId};
+use crate::common::{CardInfoId, EntryId, IdGenerator, PlayerId};
+use crate::deck_check::{DeckError, check_deck};
 use crate::effect::{Action, DoEffect, WindowsTag};
 use crate::game_diff::GameDiff;
+use crate::i18n::Dbg;
 use crate::lua_api::LuaApi;
 use crate::player::Player;
 use crate::player_actions::{PlayerAction, ReadPlayerActions};
+use crate::player_view::{CardView, Observer, PlayerView, SeatView, ZoneView};
+use crate::remote::RemoteSeat;
+use crate::ruleset::Ruleset;
+use crate::setup_error::SetupError;
+use crate::spectator::SpectatorHub;
 use crate::targeting::Targeting;
+use crate::time_control::{Deadline, PlayerClock};
+use crate::tr;
 use crate::window_event::WindowEvent;
-use log::{debug, error, info, trace, warn};
+use log::{debug, error, info, warn};
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{Rng, SeedableRng, thread_rng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-const MAX_HAND_SIZE: usize = 20;
-const MAX_COST_SIZE: usize = 6;
-const MAX_REAL_POINT: usize = 6;
+// 选择不合法时最多重新询问的次数
+const MAX_CHOICE_RETRY: usize = 3;
 
 // 游戏对象
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Game {
-    // 两个玩家
+    // 参与游戏的玩家 按座位顺序排列
     players: Vec<Player>,
     // 当前游戏阶段
     current_phase: GamePhase,
+    // 本回合剩余的阶段
+    phase_queue: VecDeque<GamePhase>,
+    // 回合数 从1开始
+    turn_count: usize,
     // 当前玩家
     current_player: PlayerId,
     // 全部card实体
@@ -38,76 +58,548 @@ pub struct Game {
     do_effect_stacks: VecDeque<DoEffect>,
     // 游戏变化
     game_diff_list: Vec<GameDiff>,
-    // 游戏结束
+    // 已被淘汰的玩家 按淘汰顺序排列
+    eliminated: Vec<(PlayerId, GameOverReason)>,
+    // 游戏结束 记录最后被淘汰的玩家
     game_over: Option<(PlayerId, GameOverReason)>,
     // AI玩家集合
     ai_players: HashSet<PlayerId>,
+    // 游戏规则
+    ruleset: Ruleset,
+    // 随机种子
+    seed: u64,
+    // 洗牌和先手使用的随机数 和 StdRng 相同的算法 可以保存状态
+    rng: ChaCha12Rng,
+    // 每位玩家的备用时间和超时次数
+    #[serde(default)]
+    clocks: Vec<PlayerClock>,
+    // 本回合操作的截止时间 第一次需要决定时开始计时 不保存
+    #[serde(skip)]
+    turn_deadline: Option<(usize, Instant)>,
+    // 当前使用的卡片定义
+    card_defs: CardDefs,
+    // 沙盒模式 重新读取卡片时场上的卡也更新
+    sandbox: bool,
+    // 重新读取卡片时使用的目录和沙盒限制 不保存
+    #[serde(skip)]
+    card_source: CardSource,
+    // 卡片目录监视 不保存
+    #[serde(skip)]
+    card_watcher: Option<CardWatcher>,
+    // 观战者 不保存
+    #[serde(skip)]
+    spectators: SpectatorHub,
+    // 通过网络控制的玩家 不保存
+    #[serde(skip)]
+    remote_players: HashMap<PlayerId, RemoteSeat>,
+    // 设置后在下一个阶段开始前停止游戏 不保存
+    #[serde(skip)]
+    stop_flag: Arc<AtomicBool>,
 }
 
 impl Game {
-    // 创建游戏
-    pub fn new(players: Vec<Player>, lua_api: &LuaApi, ai_players: HashSet<PlayerId>) -> Self {
+    // 创建游戏 卡组不符合规则时返回错误
+    pub fn new(
+        players: Vec<Player>,
+        lua_api: &LuaApi,
+        ai_players: HashSet<PlayerId>,
+        ruleset: Ruleset,
+    ) -> Result<Self, SetupError> {
+        Self::new_with_seed(players, lua_api, ai_players, ruleset, thread_rng().r#gen())
+    }
+
+    // 使用指定种子创建游戏 相同种子的洗牌和先手相同
+    pub fn new_with_seed(
+        players: Vec<Player>,
+        lua_api: &LuaApi,
+        ai_players: HashSet<PlayerId>,
+        ruleset: Ruleset,
+        seed: u64,
+    ) -> Result<Self, SetupError> {
+        let card_defs = Arc::new(lua_api.cards.clone());
+        Self::with_card_defs(players, card_defs, ai_players, ruleset, seed)
+    }
+
+    // 使用共用的卡片定义创建游戏 不复制卡片定义
+    pub fn with_card_defs(
+        players: Vec<Player>,
+        card_defs: CardDefs,
+        ai_players: HashSet<PlayerId>,
+        ruleset: Ruleset,
+        seed: u64,
+    ) -> Result<Self, SetupError> {
+        if ruleset.teams != 0 && !(2..players.len()).contains(&ruleset.teams) {
+            return Err(SetupError::Teams {
+                teams: ruleset.teams,
+                players: players.len(),
+            });
+        }
+        for player in players.iter() {
+            let violations = check_deck(&player.player_desk, &ruleset);
+            if !violations.is_empty() {
+                return Err(DeckError {
+                    player_id: player.id,
+                    violations,
+                }
+                .into());
+            }
+        }
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
         let mut id_generator = IdGenerator::new();
         let mut cards_all = Vec::new();
         let mut games_states = Vec::new();
         // 初始化卡组
         for player in players.iter() {
-            let cards = Card::init(player.player_desk.clone(), lua_api, &mut id_generator);
+            let cards = Card::init(
+                player.player_desk.clone(),
+                player.id,
+                &card_defs,
+                &mut id_generator,
+            );
             let mut game_state =
-                GameState::new(player.id.clone(), cards.clone(), &mut id_generator);
+                GameState::new(player.id, cards.clone(), &mut id_generator, &ruleset);
             // 洗牌
-            game_state.shuffle();
+            game_state.shuffle(&mut rng);
             // 抽卡
-            game_state.draw(5);
+            game_state.draw(ruleset.opening_hand, &ruleset);
             cards_all.extend(cards);
             games_states.push(game_state);
         }
-        Self {
+        // 掷硬币决定先手
+        let current_player = if players.is_empty() {
+            0
+        } else {
+            rng.gen_range(0..players.len())
+        };
+        info!("{}", tr!("种子[{}] 先手玩家[{}]", seed, current_player));
+        let mut phase_queue = ruleset.turn_phases(true);
+        let current_phase = phase_queue.pop_front().unwrap_or(GamePhase::End);
+        let clocks = vec![PlayerClock::new(ruleset.reserve_time); players.len()];
+        Ok(Self {
             players: players.clone(),
-            current_phase: GamePhase::Start,
-            current_player: 0,
+            current_phase,
+            phase_queue,
+            turn_count: 1,
+            current_player,
             cards: cards_all,
             game_states: games_states,
             id_generator,
             do_effect_stacks: VecDeque::new(),
             game_diff_list: Vec::new(),
+            eliminated: Vec::new(),
             game_over: None,
             ai_players,
+            ruleset,
+            seed,
+            rng,
+            clocks,
+            turn_deadline: None,
+            card_defs,
+            sandbox: false,
+            card_source: CardSource::default(),
+            card_watcher: None,
+            spectators: SpectatorHub::default(),
+            remote_players: HashMap::new(),
+            stop_flag: Arc::default(),
+        })
+    }
+
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.sandbox = sandbox;
+    }
+
+    /// 设置重新读取卡片时使用的目录和沙盒限制
+    pub fn set_card_source(&mut self, source: CardSource) {
+        self.card_source = source;
+    }
+
+    /// 开启后每个阶段开始前检查卡片目录 有变化时重新读取
+    pub fn watch_cards(&mut self) {
+        self.card_watcher = Some(CardWatcher::new(&self.card_source.dir));
+    }
+
+    /// 卡片目录有变化时重新读取
+    pub fn poll_card_watcher(&mut self) {
+        if let Some(watcher) = self.card_watcher.as_mut()
+            && watcher.changed()
+        {
+            info!("{}", tr!("卡片脚本有变化，重新读取"));
+            self.reload_cards();
         }
     }
 
+    /// 重新执行全部卡片脚本 有脚本出错时保留原来的定义
+    pub fn reload_cards(&mut self) -> Option<CardReload> {
+        match reload_card_defs(&self.card_source) {
+            Ok(defs) => {
+                let reload = self.apply_card_defs(defs);
+                info!(
+                    "{}",
+                    tr!(
+                        "重新读取卡片 新增{} 变化{} 删除{} 更新{}张 场上未更新{}张",
+                        Dbg(&reload.added),
+                        Dbg(&reload.changed),
+                        Dbg(&reload.removed),
+                        reload.updated,
+                        reload.skipped
+                    )
+                );
+                Some(reload)
+            }
+            Err(e) => {
+                error!("{}", tr!("重新读取失败，保留原来的卡片定义: {}", e));
+                None
+            }
+        }
+    }
+
+    // 使用新的卡片定义 场上的卡保留原来的定义 沙盒模式下全部更新
+    fn apply_card_defs(&mut self, defs: HashMap<CardInfoId, CardInfo>) -> CardReload {
+        let mut reload = diff_card_defs(&self.card_defs, &defs);
+        let in_play: HashSet<EntryId> = self
+            .game_states
+            .iter()
+            .flat_map(|state| state.zone.iter())
+            .flat_map(|zone| match zone {
+                Zone::FrontEnd { cards, .. } | Zone::BackEnd { cards, .. } => cards.iter().copied(),
+            })
+            .collect();
+        for card in self.cards.iter_mut() {
+            let id = card.card_info.id().to_string();
+            if !reload.changed.contains(&id) {
+                continue;
+            }
+            if !self.sandbox && in_play.contains(&card.entry_id) {
+                reload.skipped += 1;
+                continue;
+            }
+            card.card_info = defs[&id].clone();
+            reload.updated += 1;
+        }
+        // 被删除的定义仍然保留 已有的卡片实体还会用到 和其他对局共用时复制一份
+        Arc::make_mut(&mut self.card_defs).extend(defs);
+        reload
+    }
+
     pub fn is_ai_player(&self, player_id: PlayerId) -> bool {
         self.ai_players.contains(&player_id)
     }
 
-    pub fn get(&self, id: EntryId) -> Card {
-        self.cards
+    // 更换由AI控制的玩家 读取存档后使用
+    pub fn set_ai_players(&mut self, ai_players: HashSet<PlayerId>) {
+        self.ai_players = ai_players;
+    }
+
+    pub fn is_remote_player(&self, player_id: PlayerId) -> bool {
+        self.remote_players.contains_key(&player_id)
+    }
+
+    // 由远程连接控制玩家 代替命令行输入
+    pub fn set_remote_player(&mut self, player_id: PlayerId, seat: RemoteSeat) {
+        self.remote_players.insert(player_id, seat);
+    }
+
+    pub fn remote_player(&self, player_id: PlayerId) -> Option<RemoteSeat> {
+        self.remote_players.get(&player_id).cloned()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over.is_some()
+    }
+
+    /// 玩家这一次决定的时限 回合玩家还受本回合的时间限制 没有时间限制时为 None
+    pub fn decision_deadline(&mut self, player_id: PlayerId) -> Option<Deadline> {
+        let now = Instant::now();
+        let secs = |secs: usize| Duration::from_secs(secs as u64);
+        let mut limit =
+            (self.ruleset.decision_time > 0).then(|| now + secs(self.ruleset.decision_time));
+        if self.ruleset.turn_time > 0 && player_id == self.current_player {
+            if self
+                .turn_deadline
+                .is_none_or(|(turn, _)| turn != self.turn_count)
+            {
+                self.turn_deadline = Some((self.turn_count, now + secs(self.ruleset.turn_time)));
+            }
+            if let Some((_, turn_end)) = self.turn_deadline {
+                limit = Some(limit.map_or(turn_end, |limit| limit.min(turn_end)));
+            }
+        }
+        let reserve = self
+            .clocks
+            .get(player_id)
+            .map_or(Duration::ZERO, |clock| clock.reserve);
+        Deadline::new(player_id, now, limit, reserve)
+    }
+
+    /// 按时做出了决定 超过限制的时间从备用时间中扣除
+    pub fn finish_decision(&mut self, deadline: Option<Deadline>) {
+        if let Some(deadline) = deadline
+            && let Some(clock) = self.clocks.get_mut(deadline.player_id)
+        {
+            clock.spend(&deadline, Instant::now());
+        }
+    }
+
+    /// 超时 每回合最多记录一次超时次数 达到规则的上限时判负
+    pub fn time_out(&mut self, player_id: PlayerId) {
+        let turn = self.turn_count;
+        let Some(clock) = self.clocks.get_mut(player_id) else {
+            return;
+        };
+        clock.reserve = Duration::ZERO;
+        clock.expired += 1;
+        if clock.timeout_turn == Some(turn) {
+            return;
+        }
+        clock.timeout_turn = Some(turn);
+        clock.timeouts += 1;
+        let timeouts = clock.timeouts;
+        warn!("{}", tr!("玩家[{}] 超时{}次", player_id, timeouts));
+        if self.ruleset.max_timeouts > 0
+            && timeouts >= self.ruleset.max_timeouts
+            && self.is_alive(player_id)
+        {
+            self.eliminate(player_id, GameOverReason::Timeout);
+        }
+    }
+
+    // 包括没有记录次数的超时
+    fn expirations(&self, player_id: PlayerId) -> usize {
+        self.clocks.get(player_id).map_or(0, |clock| clock.expired)
+    }
+
+    /// 停止游戏的开关 可以在其他线程中设置 游戏在下一个阶段开始前停止
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop_flag.clone()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop_flag.load(Ordering::Relaxed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 向玩家的控制者发起选择 回答不合法时把原因告诉控制者并重新询问
+    // 只在这里检查回答 重新询问时时限不变
+    pub fn ask_choice(&mut self, player_id: PlayerId, choice: ChoiceReq) -> ChoiceRes {
+        let deadline = if self.is_ai_player(player_id) {
+            None
+        } else {
+            self.decision_deadline(player_id)
+        };
+        for _ in 0..MAX_CHOICE_RETRY {
+            let expirations = self.expirations(player_id);
+            let res = if self.is_remote_player(player_id) {
+                self.remote_read_choice(player_id, &choice, deadline)
+            } else if self.is_ai_player(player_id) {
+                Some(Ok(self.ai_read_choice(choice.clone())))
+            } else {
+                Some(Ok(self.read_choice(player_id, choice.clone(), deadline)))
+            };
+            // 超时后不再询问 使用超时的默认选择
+            if self.expirations(player_id) > expirations {
+                return self.timeout_choice(&choice);
+            }
+            // 连接断开 不再询问
+            let Some(res) = res else {
+                warn!("{}", tr!("玩家[{}] 无法回答，使用默认选择", player_id));
+                return self.default_choice(&choice);
+            };
+            match res.and_then(|res| self.check_choice(&choice, &res).map(|()| res)) {
+                Ok(res) => {
+                    self.finish_decision(deadline);
+                    return res;
+                }
+                Err(choice_error) => {
+                    if self.is_remote_player(player_id) {
+                        self.remote_read_choice_error(player_id, &choice_error);
+                    } else if self.is_ai_player(player_id) {
+                        self.ai_read_choice_error(&choice, &choice_error);
+                    } else {
+                        self.read_choice_error(&choice, &choice_error);
+                    }
+                }
+            }
+        }
+        warn!(
+            "{}",
+            tr!("玩家[{}] 多次回答不合法，使用默认选择", player_id)
+        );
+        self.default_choice(&choice)
+    }
+
+    // 起手调度 从先手开始每位玩家可以把手卡放回卡组重新抽取
+    pub fn mulligan(&mut self) {
+        let count = self.players.len();
+        for offset in 0..count {
+            let player_id = (self.current_player + offset) % count;
+            for _ in 0..self.ruleset.mulligan {
+                let ChoiceRes::Mulligan(cards) =
+                    self.ask_choice(player_id, ChoiceReq::Mulligan(player_id))
+                else {
+                    break;
+                };
+                if cards.is_empty() {
+                    break;
+                }
+                self.redraw(player_id, cards);
+            }
+        }
+    }
+
+    // 询问要回收的卡 chooser 为做出选择的玩家
+    fn choose_reuse(
+        &mut self,
+        chooser: PlayerId,
+        player_id: PlayerId,
+        limit: usize,
+    ) -> Vec<EntryId> {
+        let choice = ChoiceReq::Reuse { player_id, limit };
+        match self.ask_choice(chooser, choice) {
+            ChoiceRes::Reuse(cards) => cards,
+            _ => Vec::new(),
+        }
+    }
+
+    // 手卡放回卡组洗牌后抽取相同数量
+    fn redraw(&mut self, player_id: PlayerId, cards: Vec<EntryId>) {
+        let state = &mut self.game_states[player_id];
+        let mut returned = Vec::new();
+        for card in cards {
+            if state.hand.contains(&card) && !returned.contains(&card) {
+                returned.push(card);
+            } else {
+                warn!("{}", tr!("卡片[{}]不在玩家[{}]手卡中", card, player_id));
+            }
+        }
+        state.hand.retain(|card| !returned.contains(card));
+        state.desk.extend(returned.iter());
+        state.shuffle(&mut self.rng);
+        state.draw(returned.len(), &self.ruleset);
+        info!(
+            "{}",
+            tr!("玩家[{}] 调度 {} 张卡", player_id, returned.len())
+        );
+    }
+
+    pub fn hand(&self, player_id: PlayerId) -> Vec<EntryId> {
+        self.game_states[player_id].hand.clone()
+    }
+
+    pub fn cost_zone(&self, player_id: PlayerId) -> Vec<EntryId> {
+        self.game_states[player_id].cost.clone()
+    }
+
+    pub fn real_point(&self, player_id: PlayerId) -> usize {
+        self.game_states[player_id].real_point
+    }
+
+    // Cost区剩余可以放入手卡的位置
+    pub fn available_cost_slots(&self, player_id: PlayerId) -> usize {
+        self.ruleset
+            .max_cost_size
+            .saturating_sub(self.game_states[player_id].cost.len())
+    }
+
+    /// 玩家可以观察到的状态 其他玩家的手卡和所有卡组只有张数
+    pub fn player_view(&self, viewer: PlayerId) -> PlayerView {
+        self.view_as(Observer::Player(viewer))
+    }
+
+    /// 观战者列表 可以交给其他线程在对局进行中加入观战
+    pub fn spectators(&self) -> SpectatorHub {
+        self.spectators.clone()
+    }
+
+    // 把当前状态发送给观战者
+    fn publish_to_spectators(&self) {
+        if self.spectators.is_empty() {
+            return;
+        }
+        self.spectators
+            .publish(self.view_as(Observer::Public), self.view_as(Observer::Full));
+    }
+
+    /// 按观察者可以看到的信息生成游戏状态
+    pub fn view_as(&self, observer: Observer) -> PlayerView {
+        let cards = |ids: &Vec<EntryId>| -> Vec<CardView> {
+            ids.iter()
+                .map(|&id| CardView::of(&self.get(id), &observer))
+                .collect()
+        };
+        let seats = self
+            .game_states
             .iter()
-            .filter(|card| card.entry_id == id)
-            .next()
-            .unwrap()
-            .clone()
+            .map(|state| SeatView {
+                player_id: state.player_id,
+                alive: self.is_alive(state.player_id),
+                hp: state.hp,
+                real_point: state.real_point,
+                hand: observer
+                    .sees_hand(state.player_id)
+                    .then(|| cards(&state.hand)),
+                hand_count: state.hand.len(),
+                desk: (observer == Observer::Full).then(|| cards(&state.desk)),
+                desk_count: state.desk.len(),
+                cost: cards(&state.cost),
+                grave: cards(&state.grave),
+                zones: state
+                    .zone
+                    .iter()
+                    .map(|zone| match zone {
+                        Zone::FrontEnd { id, cards: ids } => ZoneView {
+                            id: *id,
+                            front: true,
+                            cards: cards(ids),
+                        },
+                        Zone::BackEnd { id, cards: ids } => ZoneView {
+                            id: *id,
+                            front: false,
+                            cards: cards(ids),
+                        },
+                    })
+                    .collect(),
+            })
+            .collect();
+        PlayerView {
+            observer,
+            turn: self.turn_count,
+            current_player: self.current_player,
+            phase: self.current_phase,
+            game_over: self.game_over.is_some(),
+            seats,
+        }
+    }
+
+    pub fn get(&self, id: EntryId) -> Card {
+        self.try_get(id).unwrap()
+    }
+
+    // 外部传入的实体id可能不存在
+    pub fn try_get(&self, id: EntryId) -> Option<Card> {
+        self.cards.iter().find(|card| card.entry_id == id).cloned()
     }
 
     // 返回卡片的引用
     pub fn get_mut(&mut self, id: EntryId) -> &mut Card {
         self.cards
             .iter_mut()
-            .filter(|card| card.entry_id == id)
-            .next()
+            .find(|card| card.entry_id == id)
             .unwrap()
     }
 
-    pub fn current_player(&self) -> PlayerId {
-        self.current_player
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
     }
 
-    pub fn current_grave(&self) -> Vec<EntryId> {
-        self.game_states[self.current_player].grave.clone()
-    }
-
-    pub fn current_desk_len(&self) -> usize {
-        self.game_states[self.current_player].desk.len()
+    pub fn current_player(&self) -> PlayerId {
+        self.current_player
     }
 
     pub fn current_zone(&self) -> Vec<Zone> {
@@ -121,23 +613,15 @@ impl Game {
     pub fn current_real_point(&self) -> usize {
         self.game_states[self.current_player].real_point
     }
-    pub fn current_hp(&self) -> usize {
-        self.game_states[self.current_player].hp
-    }
 
     pub fn current_cost(&self) -> Vec<EntryId> {
         self.game_states[self.current_player].cost.clone()
     }
 
-    pub fn next_cost(&self) -> Vec<EntryId> {
-        let next_player = self.next_player_id();
-        self.game_states[next_player].cost.clone()
-    }
-
     // 检查 卡片当前费用是否足够
     pub fn check_cost(&self, card_id: EntryId) -> bool {
         // Cost区满时只能用RealPoint支付
-        let available_cost_slots = MAX_COST_SIZE.saturating_sub(self.current_cost().len());
+        let available_cost_slots = self.available_cost_slots(self.current_player);
         let can_pay_by_hand = if available_cost_slots > 0 {
             self.game_states[self.current_player]
                 .hand
@@ -159,36 +643,81 @@ impl Game {
         }
     }
 
-    // 进入下一个阶段
-    fn next_phase(&mut self) {
-        self.current_phase = GamePhase::next(&self.current_phase)
+    pub fn turn_count(&self) -> usize {
+        self.turn_count
     }
 
-    // hands 支付的手牌 real_point 支付的点数
-    pub fn cost(&mut self, hands: Vec<EntryId>, real_point: usize) {
-        // Cost区满时不能用手卡支付
-        let available_slots =
-            MAX_COST_SIZE.saturating_sub(self.game_states[self.current_player].cost.len());
-
-        if available_slots == 0 && !hands.is_empty() {
-            warn!("Cost区已满，无法用手卡支付费用");
-            // 将手卡返回
-            for card in hands {
-                self.game_states[self.current_player].hand.push(card);
+    // 进入下一个阶段 本回合阶段结束时交给下一位玩家
+    fn next_phase(&mut self) {
+        let phase = match self.phase_queue.pop_front() {
+            Some(phase) => phase,
+            None => {
+                self.current_player = self.next_player_id();
+                self.turn_count += 1;
+                self.phase_queue = self.ruleset.turn_phases(false);
+                self.phase_queue.pop_front().unwrap_or(GamePhase::End)
             }
-        } else {
-            // 批量移动手卡到Cost区
-            self.game_states[self.current_player]
-                .hand
-                .retain(|x| !hands.contains(x));
+        };
+        self.current_phase = phase;
+        self.emit_event(WindowEvent::Phase { phase });
+    }
+
+    // 跳过本回合尚未进行的阶段
+    fn skip_phase(&mut self, phase: GamePhase) {
+        if let Some(index) = self.phase_queue.iter().position(|&p| p == phase) {
+            self.phase_queue.remove(index);
+            info!("{}", tr!("跳过阶段 {}", Dbg(&phase)));
+        }
+    }
 
-            let take_count = hands.len().min(available_slots);
-            for card in hands.into_iter().take(take_count) {
-                self.game_states[self.current_player].cost.push(card);
+    // 在当前阶段之后追加一个阶段
+    fn add_phase(&mut self, phase: GamePhase) {
+        self.phase_queue.push_front(phase);
+        info!("{}", tr!("追加阶段 {}", Dbg(&phase)));
+    }
+
+    // 场上的卡片及其所在玩家 已淘汰玩家的卡不再发动效果
+    fn field_cards(&self) -> Vec<(PlayerId, EntryId)> {
+        let mut ret = Vec::new();
+        for (player_id, state) in self.game_states.iter().enumerate() {
+            if !self.is_alive(player_id) {
+                continue;
+            }
+            for zone in &state.zone {
+                match zone {
+                    Zone::FrontEnd { cards, .. } | Zone::BackEnd { cards, .. } => {
+                        ret.extend(cards.iter().map(|&card| (player_id, card)));
+                    }
+                }
             }
         }
+        ret
+    }
+
+    // hands 支付的手牌 real_point 支付的点数 支付不合法时不做任何改变
+    pub fn cost(&mut self, hands: Vec<EntryId>, real_point: usize) -> Result<(), ChoiceError> {
+        // Cost区满时不能用手卡支付
+        let slots = self.available_cost_slots(self.current_player);
+        if hands.len() > slots {
+            return Err(ChoiceError::CostZoneFull {
+                slots,
+                hands: hands.len(),
+            });
+        }
+        let state = &mut self.game_states[self.current_player];
+        check_cards(&hands, &state.hand, ChoiceError::NotInHand)?;
+        let Some(rest) = state.real_point.checked_sub(real_point) else {
+            return Err(ChoiceError::NotEnoughRealPoint {
+                need: real_point,
+                have: state.real_point,
+            });
+        };
+        // 批量移动手卡到Cost区
+        state.hand.retain(|x| !hands.contains(x));
+        state.cost.extend(hands);
         // 减少RealPoint
-        self.game_states[self.current_player].real_point -= real_point;
+        state.real_point = rest;
+        Ok(())
     }
 
     fn check_attack_action(&self, player_action: PlayerAction) -> bool {
@@ -197,38 +726,43 @@ impl Game {
                 if let Some(for_zone) = self.game_states[self.current_player]
                     .zone
                     .iter()
-                    .filter(|zone| zone.has_id(zone_id) && zone.has_cards())
-                    .next()
+                    .find(|zone| zone.has_id(zone_id) && zone.has_cards())
                 {
                     return if self.zone_can_attack(for_zone) {
-                        let vec = self.get_attacked_zones();
-                        if vec.len() > 0 {
-                            // 存在攻击区域
-                            if let Targeting::TargetZone(target_id) = target {
+                        if let Targeting::TargetZone(target_id) = target {
+                            // 攻击对手前场的卡
+                            if self
+                                .get_attacked_zones()
+                                .iter()
+                                .any(|zone| zone.has_id(target_id))
+                            {
                                 true
                             } else {
-                                error!("进攻目标不正确");
+                                error!("{}", tr!("进攻目标不正确"));
                                 false
                             }
-                        } else {
-                            // 直接攻击玩家
-                            if let Targeting::TargetPlayerOpponent = target {
+                        } else if let Some(player_id) = self.attacked_player(&target) {
+                            // 对手前场没有卡时才能直接攻击玩家
+                            if self.attacked_zones_of(player_id).is_empty() {
                                 true
                             } else {
-                                error!("进攻目标不正确");
+                                error!("{}", tr!("玩家[{}]前场还有卡，不能直接攻击", player_id));
                                 false
                             }
+                        } else {
+                            error!("{}", tr!("进攻目标不正确"));
+                            false
                         }
                     } else {
-                        error!("当前区域不能攻击");
+                        error!("{}", tr!("当前区域不能攻击"));
                         false
                     };
                 } else {
-                    error!("来源位置不存在");
+                    error!("{}", tr!("来源位置不存在"));
                     return false;
                 }
             } else {
-                error!("攻击的卡选择的类型不正确");
+                error!("{}", tr!("攻击的卡选择的类型不正确"));
                 return false;
             }
         }
@@ -239,19 +773,29 @@ impl Game {
         match player_acton.clone() {
             // 放置卡片
             PlayerAction::SetCard { card_id, zone_id } => {
+                // 只能登场自己手卡中的卡
+                let in_hand = self.hand(self.current_player).contains(&card_id);
+                let Some(card) = self.try_get(card_id).filter(|_| in_hand) else {
+                    error!(
+                        "{}",
+                        tr!("卡片[{}]不在玩家[{}]手卡中", card_id, self.current_player)
+                    );
+                    return;
+                };
                 self.game_states[self.current_player]
                     .hand
                     .retain(|&x| x != card_id);
 
-                let card = self.get(card_id);
+                // 检查费用是否足够
+                if !self.check_cost(card_id) {
+                    error!("{}", tr!("无法支付费用，卡片返回原处"));
+                    self.set_rollback(card_id);
+                    return;
+                }
 
                 // 支持不支付费用的登场
                 let choice_res = if card.card_info.cost > 0 {
-                    if self.is_ai_player(self.current_player) {
-                        self.ai_read_choice(ChoiceReq::Cost(card_id))
-                    } else {
-                        self.read_choice(ChoiceReq::Cost(card_id))
-                    }
+                    self.ask_choice(self.current_player, ChoiceReq::Cost(card_id))
                 } else {
                     ChoiceRes::Cost {
                         hands: Vec::new(),
@@ -260,13 +804,20 @@ impl Game {
                 };
 
                 if let ChoiceRes::Cost { hands, real_point } = choice_res {
-                    self.cost(hands, real_point);
+                    if let Err(e) = self.cost(hands, real_point) {
+                        error!("{}", tr!("无法支付费用: {}，卡片返回原处", e));
+                        self.set_rollback(card_id);
+                        return;
+                    }
                     self.do_effect_stacks.push_front(DoEffect::Action {
                         source: Targeting::TargetPlayerSelf,
                         targeting: Targeting::None,
                         action: Action::Set { card_id, zone_id },
                     });
                     self.process_effect();
+                } else {
+                    info!("{}", tr!("取消登场"));
+                    self.set_rollback(card_id);
                 }
             }
             PlayerAction::EffectCard { .. } => {}
@@ -293,63 +844,61 @@ impl Game {
         // 如果源不存在了 就停止
         // 如果目标不存在了 或者有了新的目标 就回滚战斗需要询问对手
         // 进入战斗阶段
-        if let Targeting::TargetZone(zone_id) = source {
-            if let Some(for_zone) = self.game_states[self.current_player]
+        if let Targeting::TargetZone(zone_id) = source
+            && let Some(_for_zone) = self.game_states[self.current_player]
                 .zone
                 .iter()
-                .filter(|zone| zone.has_id(zone_id) && zone.has_cards())
-                .next()
-            {
-                let attacked_zones = self.get_attacked_zones();
-
-                // 卡片和卡片进行战斗
-                if let Targeting::TargetZone(target_id) = target {
-                    // 判断 这个zoneId下是不是没有卡了
-                    if let Some(_) = attacked_zones
-                        .iter()
-                        .filter(|zone| zone.has_id(target_id))
-                        .next()
-                    {
-                        // 找到了进行结算
-                        self.deal_fight_zone(zone_id, target_id);
-                    } else {
-                        // 没有找到进行询问
-                    }
+                .find(|zone| zone.has_id(zone_id) && zone.has_cards())
+        {
+            let attacked_zones = self.get_attacked_zones();
+
+            // 卡片和卡片进行战斗
+            if let Targeting::TargetZone(target_id) = target {
+                // 判断 这个zoneId下是不是没有卡了
+                if attacked_zones
+                    .iter()
+                    .find(|zone| zone.has_id(target_id))
+                    .is_some()
+                {
+                    // 找到了进行结算
+                    self.deal_fight_zone(zone_id, target_id);
+                } else {
+                    // 没有找到进行询问
                 }
+            }
 
-                // 卡片直接攻击玩家
-                if let Targeting::TargetPlayerOpponent = target {
-                    // 判断这 zoneId是不是还有卡
-                    if attacked_zones.len() > 0 {
-                        // 进行询问
-                    } else {
-                        // 进行结算
-                        self.deal_fight_direct(zone_id);
-                    }
+            // 卡片直接攻击玩家
+            if let Some(player_id) = self.attacked_player(&target) {
+                // 判断这个玩家的前场是不是还有卡
+                if !self.attacked_zones_of(player_id).is_empty() {
+                    // 进行询问
+                } else {
+                    // 进行结算
+                    self.deal_fight_direct(zone_id, player_id);
                 }
             }
         }
     }
 
     // 处理直接攻击的情况
-    fn deal_fight_direct(&mut self, my_zone: EntryId) {
+    fn deal_fight_direct(&mut self, my_zone: EntryId, target_player: PlayerId) {
         // 获取卡片的信息
         // effect 增加攻击计数
 
         if let Some(zone) = self.get_my_zone(my_zone) {
             match zone {
-                Zone::FrontEnd { id, cards } => {
+                Zone::FrontEnd { id: _, cards } => {
                     if let Some(card_id) = cards.first() {
                         // 攻击计数+1
                         self.do_effect_stacks.push_front(DoEffect::Action {
                             source: Targeting::None,
                             targeting: Targeting::None,
-                            action: Action::AttackCounterUp(card_id.clone(), 1),
+                            action: Action::AttackCounterUp(*card_id, 1),
                         });
 
                         if self.current_real_point() == 0 {
                             // 如果 realPoint = 0 realPoint +1
-                            info!("当前无RealPoint。");
+                            info!("{}", tr!("当前无RealPoint。"));
                             self.do_effect_stacks.push_front(DoEffect::Action {
                                 source: Default::default(),
                                 targeting: Targeting::TargetPlayerSelf,
@@ -357,10 +906,11 @@ impl Game {
                             });
                         } else {
                             // 如果 realPoint > 0 询问 是否要使用 如果使用了 则 伤害 扣除 RealPoint
-                            info!("当前有RealPoint。询问如何使用");
-                            let choice_res = self.read_fight_damage();
+                            info!("{}", tr!("当前有RealPoint。询问如何使用"));
+                            let choice_res =
+                                self.ask_choice(self.current_player, ChoiceReq::FightDamage);
                             match choice_res {
-                                ChoiceRes::None => {
+                                ChoiceRes::None | ChoiceRes::FightDamageByRealPoint(0) => {
                                     self.do_effect_stacks.push_front(DoEffect::Action {
                                         source: Default::default(),
                                         targeting: Targeting::TargetPlayerSelf,
@@ -376,8 +926,8 @@ impl Game {
                                     });
                                     // 造成伤害
                                     self.do_effect_stacks.push_front(DoEffect::Action {
-                                        source: Targeting::TargetZone(my_zone.clone()),
-                                        targeting: Targeting::TargetPlayerOpponent,
+                                        source: Targeting::TargetZone(my_zone),
+                                        targeting: Targeting::TargetPlayer(target_player),
                                         action: Action::Damage(num),
                                     });
                                 }
@@ -387,7 +937,7 @@ impl Game {
                     }
                 }
                 Zone::BackEnd { .. } => {
-                    warn!("后场卡不战斗");
+                    warn!("{}", tr!("后场卡不战斗"));
                 }
             }
         }
@@ -395,95 +945,103 @@ impl Game {
 
     // 计算的发生的战斗
     fn deal_fight_zone(&mut self, my_zone_id: EntryId, target_zone_id: EntryId) {
-        if let Some(my_zone) = self.get_my_zone(my_zone_id) {
-            if let Some(target_zone) = self.get_other_zone(target_zone_id) {
-                if let Zone::FrontEnd {
-                    id: _id,
-                    cards: my_cards,
-                } = my_zone
-                {
-                    if let Zone::FrontEnd {
-                        id: _id,
-                        cards: target_cards,
-                    } = target_zone
-                    {
-                        let card = self.get(my_cards.first().unwrap().clone());
-                        let target_card = self.get(target_cards.first().unwrap().clone());
-                        if card.card_info.ack > target_card.card_info.ack {
-                            // 攻击胜利
-                            info!(
-                                "战斗胜利 {:?} > {:?}",
-                                card.card_info.ack, target_card.card_info.ack
-                            );
-                            // 攻击计数+1
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::None,
-                                action: Action::AttackCounterUp(card.entry_id, 1),
-                            });
-                            // 破坏对手卡
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::None,
-                                action: Action::FightDestroy {
-                                    zone_id: target_zone_id,
-                                },
-                            });
-                            // 增加点数
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::TargetPlayerSelf,
-                                action: Action::AddRealPoint(1),
-                            });
-                        } else if card.card_info.ack == target_card.card_info.ack {
-                            info!(
-                                "战斗平手 {:?} = {:?}",
-                                card.card_info.ack, target_card.card_info.ack
-                            );
-                            // 平手
-                            // 两张卡都破坏
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::None,
-                                action: Action::FightDestroy {
-                                    zone_id: target_zone_id,
-                                },
-                            });
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::None,
-                                action: Action::FightDestroy {
-                                    zone_id: my_zone_id,
-                                },
-                            });
-                            // 增加点数
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::TargetPlayerSelf,
-                                action: Action::AddRealPoint(1),
-                            });
-                        } else {
-                            info!(
-                                "战斗失败 {:?} = {:?}",
-                                card.card_info.ack, target_card.card_info.ack
-                            );
-                            // 破坏自己卡
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::None,
-                                action: Action::FightDestroy {
-                                    zone_id: my_zone_id,
-                                },
-                            });
-                            // 增加点数
-                            self.do_effect_stacks.push_front(DoEffect::Action {
-                                source: Targeting::None,
-                                targeting: Targeting::TargetPlayerSelf,
-                                action: Action::AddRealPoint(1),
-                            });
-                        }
-                    }
-                }
+        if let Some(my_zone) = self.get_my_zone(my_zone_id)
+            && let Some(target_zone) = self.get_other_zone(target_zone_id)
+            && let Zone::FrontEnd {
+                id: _id,
+                cards: my_cards,
+            } = my_zone
+            && let Zone::FrontEnd {
+                id: _id,
+                cards: target_cards,
+            } = target_zone
+        {
+            let card = self.get(*my_cards.first().unwrap());
+            let target_card = self.get(*target_cards.first().unwrap());
+            if card.card_info.ack > target_card.card_info.ack {
+                // 攻击胜利
+                info!(
+                    "{}",
+                    tr!(
+                        "战斗胜利 {} > {}",
+                        Dbg(&card.card_info.ack),
+                        Dbg(&target_card.card_info.ack)
+                    )
+                );
+                // 攻击计数+1
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::None,
+                    action: Action::AttackCounterUp(card.entry_id, 1),
+                });
+                // 破坏对手卡
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::None,
+                    action: Action::FightDestroy {
+                        zone_id: target_zone_id,
+                    },
+                });
+                // 增加点数
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::TargetPlayerSelf,
+                    action: Action::AddRealPoint(1),
+                });
+            } else if card.card_info.ack == target_card.card_info.ack {
+                info!(
+                    "{}",
+                    tr!(
+                        "战斗平手 {} = {}",
+                        Dbg(&card.card_info.ack),
+                        Dbg(&target_card.card_info.ack)
+                    )
+                );
+                // 平手
+                // 两张卡都破坏
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::None,
+                    action: Action::FightDestroy {
+                        zone_id: target_zone_id,
+                    },
+                });
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::None,
+                    action: Action::FightDestroy {
+                        zone_id: my_zone_id,
+                    },
+                });
+                // 增加点数
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::TargetPlayerSelf,
+                    action: Action::AddRealPoint(1),
+                });
+            } else {
+                info!(
+                    "{}",
+                    tr!(
+                        "战斗失败 {} = {}",
+                        Dbg(&card.card_info.ack),
+                        Dbg(&target_card.card_info.ack)
+                    )
+                );
+                // 破坏自己卡
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::None,
+                    action: Action::FightDestroy {
+                        zone_id: my_zone_id,
+                    },
+                });
+                // 增加点数
+                self.do_effect_stacks.push_front(DoEffect::Action {
+                    source: Targeting::None,
+                    targeting: Targeting::TargetPlayerSelf,
+                    action: Action::AddRealPoint(1),
+                });
             }
         }
     }
@@ -492,78 +1050,179 @@ impl Game {
         self.game_states[self.current_player]
             .zone
             .iter()
-            .filter(|zone| zone.has_id(id))
-            .next()
+            .find(|zone| zone.has_id(id))
     }
 
     fn get_other_zone(&self, id: EntryId) -> Option<&Zone> {
-        self.game_states[self.next_player_id()]
-            .zone
-            .iter()
-            .filter(|zone| zone.has_id(id))
-            .next()
+        self.opponents_of(self.current_player)
+            .into_iter()
+            .find_map(|player_id| {
+                self.game_states[player_id]
+                    .zone
+                    .iter()
+                    .find(|zone| zone.has_id(id))
+            })
     }
 
-    fn remove_my_zone_cards(&mut self, id: EntryId) -> Vec<EntryId> {
+    // 移除区域内的卡 区域id在整局游戏中唯一
+    fn remove_zone_cards(&mut self, id: EntryId) -> Vec<EntryId> {
         let mut ret = Vec::new();
-        if let Some(zone) = self.game_states[self.current_player]
-            .zone
-            .iter_mut()
-            .filter(|zone| zone.has_id(id))
-            .next()
-        {
-            match zone {
-                Zone::FrontEnd { id, cards } => {
-                    ret.extend(cards.drain(..));
-                }
-                Zone::BackEnd { id, cards } => {
-                    ret.extend(cards.drain(..));
+        for state in self.game_states.iter_mut() {
+            if let Some(zone) = state.zone.iter_mut().find(|zone| zone.has_id(id)) {
+                match zone {
+                    Zone::FrontEnd { id: _, cards } => {
+                        ret.append(cards);
+                    }
+                    Zone::BackEnd { id: _, cards } => {
+                        ret.append(cards);
+                    }
                 }
             }
         }
         ret
     }
 
-    fn remove_other_zone_cards(&mut self, id: EntryId) -> Vec<EntryId> {
-        let mut ret = Vec::new();
-        let next_id = self.next_player_id();
-        if let Some(zone) = self.game_states[next_id]
-            .zone
-            .iter_mut()
-            .filter(|zone| zone.has_id(id))
-            .next()
-        {
-            match zone {
-                Zone::FrontEnd { id, cards } => {
-                    ret.extend(cards.drain(..));
-                }
-                Zone::BackEnd { id, cards } => {
-                    ret.extend(cards.drain(..));
-                }
+    // 区域所属的玩家
+    fn zone_owner(&self, zone_id: EntryId) -> Option<PlayerId> {
+        self.game_states
+            .iter()
+            .position(|state| state.zone.iter().any(|zone| zone.has_id(zone_id)))
+    }
+
+    // 效果的控制者 卡片发动的效果由卡片的控制者结算 其他效果属于回合玩家
+    fn effect_controller(&self, source: &Targeting) -> PlayerId {
+        match source {
+            Targeting::TargetCard(card_id) => self.get(*card_id).controller,
+            Targeting::TargetZone(zone_id) => {
+                self.zone_owner(*zone_id).unwrap_or(self.current_player)
             }
+            _ => self.current_player,
+        }
+    }
+
+    // 玩家是否还在游戏中
+    pub fn is_alive(&self, player_id: PlayerId) -> bool {
+        !self.eliminated.iter().any(|(id, _)| *id == player_id)
+    }
+
+    // 两位玩家是否属于同一队伍
+    pub fn is_teammate(&self, a: PlayerId, b: PlayerId) -> bool {
+        self.ruleset.team_of(a) == self.ruleset.team_of(b)
+    }
+
+    // 玩家的全部对手 按座位顺序从下家开始 不包括已淘汰的玩家
+    pub fn opponents_of(&self, player_id: PlayerId) -> Vec<PlayerId> {
+        let count = self.players.len();
+        (1..count)
+            .map(|offset| (player_id + offset) % count)
+            .filter(|&id| self.is_alive(id) && !self.is_teammate(player_id, id))
+            .collect()
+    }
+
+    // 玩家的对手 有多个对手时为座位顺序上最近的一位 没有对手时为 None
+    pub fn opponent_of(&self, player_id: PlayerId) -> Option<PlayerId> {
+        self.opponents_of(player_id).first().copied()
+    }
+
+    // 相对于控制者解析目标玩家
+    fn target_player(&self, controller: PlayerId, targeting: &Targeting) -> Option<PlayerId> {
+        match targeting {
+            Targeting::TargetPlayerSelf => Some(controller),
+            Targeting::TargetPlayerOpponent => self.opponent_of(controller),
+            Targeting::TargetPlayer(player_id) if *player_id < self.players.len() => {
+                Some(*player_id)
+            }
+            _ => None,
         }
-        ret
+    }
+
+    // 直接攻击的目标玩家 只能是当前玩家的对手
+    fn attacked_player(&self, target: &Targeting) -> Option<PlayerId> {
+        let player_id = match target {
+            Targeting::TargetPlayerOpponent => self.opponent_of(self.current_player)?,
+            Targeting::TargetPlayer(player_id) => *player_id,
+            _ => return None,
+        };
+        self.opponents_of(self.current_player)
+            .contains(&player_id)
+            .then_some(player_id)
+    }
+
+    // 淘汰玩家 只剩一支队伍时游戏结束 返回true表示游戏结束
+    fn eliminate(&mut self, player_id: PlayerId, reason: GameOverReason) -> bool {
+        if self.is_alive(player_id) {
+            info!(
+                "{}",
+                tr!("玩家[{}]被淘汰 原因: {}", player_id, Dbg(&reason))
+            );
+            self.eliminated.push((player_id, reason.clone()));
+            let teams: HashSet<usize> = (0..self.players.len())
+                .filter(|&id| self.is_alive(id))
+                .map(|id| self.ruleset.team_of(id))
+                .collect();
+            if teams.len() <= 1 {
+                self.game_over = Some((player_id, reason));
+            }
+        }
+        self.game_over.is_some()
+    }
+
+    // 仍在游戏中的玩家
+    pub fn alive_players(&self) -> Vec<PlayerId> {
+        (0..self.players.len())
+            .filter(|&id| self.is_alive(id))
+            .collect()
     }
 
     pub fn emit_event(&mut self, window_event: WindowEvent) {
         match window_event {
-            WindowEvent::Cost { card } => {
+            WindowEvent::Cost { card: _ } => {
                 // 这里实现登场时效果事件 这里要有一个自排连锁的问题
-                warn!("TODO");
+                warn!("{}", tr!("TODO"));
             }
             WindowEvent::Set { card } => {
                 let card_instance = self.get(card);
                 for effect in card_instance.card_info.effects {
                     if effect.windows_tag == WindowsTag::OnSet {
                         // todo 这里在思考更加复杂的 情况
-                        info!("登场时发动效果：{:?}", effect.do_effect);
-                        self.do_effect_stacks.push_front(effect.do_effect.clone());
+                        info!("{}", tr!("登场时发动效果：{}", Dbg(&effect.do_effect)));
+                        self.do_effect_stacks
+                            .push_front(effect.do_effect.with_source(Targeting::TargetCard(card)));
                     }
                 }
             }
-            WindowEvent::Attack { source, target } => {
+            WindowEvent::Attack {
+                source: _,
+                target: _,
+            } => {
                 // 处理攻击时的效果
-                warn!("Attack Event TODO");
+                warn!("{}", tr!("Attack Event TODO"));
+            }
+            WindowEvent::Phase { phase } => {
+                for (_, card) in self.field_cards() {
+                    let card_instance = self.get(card);
+                    let is_self_turn = card_instance.controller == self.current_player;
+                    for effect in card_instance.card_info.effects {
+                        let triggered = match effect.windows_tag {
+                            WindowsTag::OnStart => phase == GamePhase::Start,
+                            WindowsTag::OnSelfStart => phase == GamePhase::Start && is_self_turn,
+                            WindowsTag::OnOpponentStart => {
+                                phase == GamePhase::Start && !is_self_turn
+                            }
+                            WindowsTag::OnPhase(tag_phase) => phase == tag_phase,
+                            _ => false,
+                        };
+                        if triggered {
+                            info!(
+                                "{}",
+                                tr!("阶段{}发动效果：{}", Dbg(&phase), Dbg(&effect.do_effect))
+                            );
+                            self.do_effect_stacks.push_front(
+                                effect.do_effect.with_source(Targeting::TargetCard(card)),
+                            );
+                        }
+                    }
+                }
             }
         }
         self.process_effect();
@@ -574,247 +1233,232 @@ impl Game {
         while let Some(event) = self.do_effect_stacks.pop_front() {
             match event {
                 DoEffect::None => {
-                    warn!("Do effect DoNothing!");
+                    warn!("{}", tr!("Do effect DoNothing!"));
                 }
                 DoEffect::Action {
                     source,
                     targeting,
                     action,
-                } => match action {
-                    Action::None => {
-                        debug!("Do effect DoNothing!");
-                    }
-                    Action::Draw(num) => match targeting {
-                        Targeting::TargetPlayerSelf => {
-                            let deck_out = self.game_states[self.current_player].draw(num);
-                            if deck_out {
-                                self.game_over =
-                                    Some((self.current_player, GameOverReason::DeckOut));
-                                return;
-                            }
+                } => {
+                    // 自己和对手都相对于效果的控制者
+                    let controller = self.effect_controller(&source);
+                    let target_player = self.target_player(controller, &targeting);
+                    match action {
+                        Action::None => {
+                            debug!("{}", tr!("Do effect DoNothing!"));
                         }
-                        Targeting::TargetPlayerOpponent => {
-                            let i = self.next_player_id();
-                            let deck_out = self.game_states[i].draw(num);
-                            if deck_out {
-                                self.game_over = Some((i, GameOverReason::DeckOut));
-                                return;
+                        Action::Draw(num) => {
+                            if let Some(player_id) = target_player {
+                                let deck_out = self.game_states[player_id].draw(num, &self.ruleset);
+                                if deck_out && self.eliminate(player_id, GameOverReason::DeckOut) {
+                                    return;
+                                }
                             }
                         }
-                        _ => {}
-                    },
-                    Action::Set { card_id, zone_id } => {
-                        // 登场卡片
-                        info!("卡片登场!Card {:?} 登场到 Zone {:?}", card_id, zone_id);
-                        // 原来的位置 要取消数据
-                        // 如果Cost 里面有的情况要取消
-                        self.game_states[self.current_player]
-                            .cost
-                            .retain(|&x| x != card_id);
-                        let mut flag = false;
-                        for zone in self.game_states[self.current_player.clone()]
-                            .zone
-                            .iter_mut()
-                        {
-                            match zone {
-                                Zone::FrontEnd { id, cards } => {
-                                    if *id == zone_id {
-                                        cards.push(card_id);
-                                        flag = true;
+                        Action::Set { card_id, zone_id } => {
+                            // 登场卡片
+                            info!(
+                                "{}",
+                                tr!(
+                                    "卡片登场!Card {} 登场到 Zone {}",
+                                    Dbg(&card_id),
+                                    Dbg(&zone_id)
+                                )
+                            );
+                            // 原来的位置 要取消数据
+                            // 如果Cost 里面有的情况要取消
+                            self.game_states[controller].cost.retain(|&x| x != card_id);
+                            let mut flag = false;
+                            for zone in self.game_states[controller].zone.iter_mut() {
+                                match zone {
+                                    Zone::FrontEnd { id, cards } => {
+                                        if *id == zone_id {
+                                            cards.push(card_id);
+                                            flag = true;
+                                        }
                                     }
-                                }
-                                Zone::BackEnd { id, cards } => {
-                                    if *id == zone_id {
-                                        cards.push(card_id);
+                                    Zone::BackEnd { id, cards } => {
+                                        if *id == zone_id {
+                                            cards.push(card_id);
+                                        }
                                     }
                                 }
                             }
+                            // 登场的玩家成为卡片的控制者
+                            self.get_mut(card_id).controller = controller;
+                            // 抛出登场时事件
+                            if flag {
+                                self.emit_event(WindowEvent::Set { card: card_id });
+                            }
                         }
-                        // 抛出登场时事件
-                        if flag {
-                            self.emit_event(WindowEvent::Set { card: card_id });
+                        Action::Damage(num) => {
+                            if let Some(player_id) = target_player
+                                && self.damage(player_id, num)
+                            {
+                                return;
+                            }
                         }
-                    }
-                    Action::Damage(num) => {
-                        if let Targeting::TargetPlayerSelf = targeting {
-                            let real_point = self.game_states[self.current_player].real_point;
-                            let hp = self.game_states[self.current_player].hp;
-                            if hp <= num {
-                                warn!("当前玩家生命值不足");
-                                if real_point + hp > num {
-                                    info!("使用RealPoint保护生命");
-                                    self.game_states[self.current_player].hp = 1;
-                                    self.game_states[self.current_player].real_point =
-                                        real_point - (num - hp + 1);
+                        Action::AddRealPoint(num) => {
+                            if let Some(player_id) = target_player {
+                                let current = self.game_states[player_id].real_point;
+                                let max_real_point = self.ruleset.max_real_point;
+                                if current >= max_real_point {
+                                    warn!(
+                                        "{}",
+                                        tr!("RealPoint已达到上限[{}]，不再增加", max_real_point)
+                                    );
                                 } else {
-                                    self.game_states[self.current_player].hp = 0;
-                                    self.game_states[self.current_player].real_point = 0;
-                                    self.game_over =
-                                        Some((self.current_player, GameOverReason::HpZero));
-                                    return;
+                                    let new_value = (current + num).min(max_real_point);
+                                    self.game_states[player_id].real_point = new_value;
+                                    info!(
+                                        "{}",
+                                        tr!(
+                                            "玩家[{}]RealPoint 增加[{}]，当前[{}]",
+                                            player_id,
+                                            Dbg(&num),
+                                            Dbg(&new_value)
+                                        )
+                                    );
                                 }
-                            } else {
-                                self.game_states[self.current_player].hp -= num;
+                            }
+                        }
+                        Action::UseRealPoint(num) => {
+                            if let Some(player_id) = target_player
+                                && self.game_states[player_id].real_point >= num
+                            {
+                                self.game_states[player_id].real_point -= num;
                                 info!(
-                                    "伤害后的生命值hp[{:?}]",
-                                    self.game_states[self.current_player].hp
+                                    "{}",
+                                    tr!("玩家[{}]RealPoint 减少[{}]", player_id, Dbg(&num))
                                 );
                             }
                         }
-                        if let Targeting::TargetPlayerOpponent = targeting {
-                            let next_id = self.next_player_id();
-                            let real_point = self.game_states[next_id].real_point;
-                            let hp = self.game_states[next_id].hp;
-                            if hp <= num {
-                                warn!("对方玩家生命值不足");
-                                if real_point + hp > num {
-                                    info!("使用RealPoint保护生命");
-                                    self.game_states[next_id].hp = 1;
-                                    self.game_states[next_id].real_point =
-                                        real_point - (num - hp + 1);
-                                } else {
-                                    self.game_states[next_id].hp = 0;
-                                    self.game_states[next_id].real_point = 0;
-                                    self.game_over = Some((next_id, GameOverReason::HpZero));
-                                    return;
-                                }
-                            } else {
-                                self.game_states[next_id].hp -= num;
-                                info!("伤害后的生命值hp[{:?}]", self.game_states[next_id].hp);
-                            }
+                        // 战斗破坏
+                        Action::FightDestroy { zone_id } => {
+                            let cards = self.remove_zone_cards(zone_id);
+                            self.destroy_zone(cards);
                         }
-                    }
-                    Action::AddRealPoint(num) => {
-                        if let Targeting::TargetPlayerSelf = targeting {
-                            let current = self.game_states[self.current_player].real_point;
-                            if current >= MAX_REAL_POINT {
-                                warn!("RealPoint已达到上限[{}]，不再增加", MAX_REAL_POINT);
-                            } else {
-                                let new_value = (current + num).min(MAX_REAL_POINT);
-                                self.game_states[self.current_player].real_point = new_value;
-                                info!("当前玩家RealPoint 增加[{:?}]，当前[{:?}]", num, new_value);
-                            }
+                        Action::AttackCounterUp(card_id, num) => {
+                            let card = self.get_mut(card_id);
+                            card.attack_counter += num;
                         }
-                        if let Targeting::TargetPlayerOpponent = targeting {
-                            let next_id = self.next_player_id();
-                            let current = self.game_states[next_id].real_point;
-                            if current >= MAX_REAL_POINT {
-                                warn!("RealPoint已达到上限[{}]，不再增加", MAX_REAL_POINT);
+                        Action::AttackCountDown(card_id, num) => {
+                            let card = self.get_mut(card_id);
+                            if card.attack_counter >= num {
+                                card.attack_counter -= num;
                             } else {
-                                let new_value = (current + num).min(MAX_REAL_POINT);
-                                self.game_states[next_id].real_point = new_value;
-                                info!("对方玩家RealPoint 增加[{:?}]，当前[{:?}]", num, new_value);
+                                card.attack_counter = 0;
                             }
                         }
-                    }
-                    Action::UseRealPoint(num) => {
-                        if let Targeting::TargetPlayerSelf = targeting {
-                            if self.game_states[self.current_player].real_point >= num {
-                                self.game_states[self.current_player].real_point -= num;
-                                info!("当前玩家RealPoint 减少[{:?}]", num);
+                        Action::AskingReuse(limit) => {
+                            // 由效果的控制者选择回收哪些卡 没有对手时不回收
+                            let target_id = match targeting {
+                                Targeting::TargetPlayerOpponent => target_player,
+                                _ => Some(target_player.unwrap_or(controller)),
+                            };
+                            if let Some(target_id) = target_id {
+                                let choice_cards = self.choose_reuse(controller, target_id, limit);
+                                self.do_effect_stacks.push_front(DoEffect::Action {
+                                    source,
+                                    targeting,
+                                    action: Action::Reuse(choice_cards),
+                                });
                             }
                         }
-                        if let Targeting::TargetPlayerOpponent = targeting {
-                            let next_id = self.next_player_id();
-                            if self.game_states[next_id].real_point >= num {
-                                self.game_states[next_id].real_point -= num;
-                                info!("对方玩家RealPoint 减少[{:?}]", num);
+                        Action::Reuse(cost_cards) => {
+                            // 回收卡片进手卡
+                            info!("{}", tr!("回收卡片进手卡"));
+                            if let Some(player_id) = target_player {
+                                self.game_states[player_id]
+                                    .cost
+                                    .retain(|x| !cost_cards.contains(x));
+                                self.game_states[player_id].hand.extend(cost_cards);
                             }
                         }
-                    }
-                    // 战斗破坏
-                    Action::FightDestroy { zone_id } => {
-                        let cards = self.remove_my_zone_cards(zone_id);
-                        self.destroy_zone(cards, Targeting::TargetPlayerSelf);
-
-                        let cards = self.remove_other_zone_cards(zone_id);
-                        self.destroy_zone(cards, Targeting::TargetPlayerOpponent);
-                    }
-                    Action::AttackCounterUp(card_id, num) => {
-                        let card = self.get_mut(card_id);
-                        card.attack_counter += num;
-                    }
-                    Action::AttackCountDown(card_id, num) => {
-                        let card = self.get_mut(card_id);
-                        if card.attack_counter >= num {
-                            card.attack_counter -= num;
-                        } else {
-                            card.attack_counter = 0;
+                        Action::SkipPhase(phase) => {
+                            self.skip_phase(phase);
                         }
-                    }
-                    Action::AskingReuse(limit) => {
-                        // todo 向需要的人询问
-                        let choice_cards = self.read_reuse_choice(targeting.clone(), limit);
-                        self.do_effect_stacks.push_front(DoEffect::Action {
-                            source: Default::default(),
-                            targeting,
-                            action: Action::Reuse(choice_cards),
-                        });
-                    }
-                    Action::Reuse(cost_cards) => {
-                        // 回收卡片进手卡
-                        info!("回收卡片进手卡");
-                        if let Targeting::TargetPlayerSelf = targeting {
-                            // 自己的卡回收
-                            self.game_states[self.current_player]
-                                .cost
-                                .retain(|x| !cost_cards.contains(x));
-                            self.game_states[self.current_player]
-                                .hand
-                                .extend(cost_cards);
-                        } else if let Targeting::TargetPlayerOpponent = targeting {
-                            let next_id = self.next_player_id();
-                            // 对手的卡回收
-                            self.game_states[next_id]
-                                .cost
-                                .retain(|x| !cost_cards.contains(x));
-                            self.game_states[next_id].hand.extend(cost_cards);
+                        Action::AddPhase(phase) => {
+                            self.add_phase(phase);
                         }
                     }
-                },
+                }
                 DoEffect::AndAction(actions) => {
-                    for action in actions {
-                        self.do_effect_stacks.push_back(action);
+                    // 在已经等待的效果之前依次执行
+                    for action in actions.into_iter().rev() {
+                        self.do_effect_stacks.push_front(action);
                     }
                 }
-                DoEffect::OrAction(_) => {
-                    // todo 需要用户选择
+                DoEffect::OrAction(mut actions) => {
+                    if actions.is_empty() {
+                        continue;
+                    }
+                    // 效果的控制者选择执行哪一项
+                    let controller = self.effect_controller(&actions[0].source());
+                    let choice = ChoiceReq::OrAction(actions.clone());
+                    if let ChoiceRes::OrAction(index) = self.ask_choice(controller, choice) {
+                        self.do_effect_stacks.push_front(actions.swap_remove(index));
+                    }
                 }
             }
         }
     }
 
-    // 破坏场地上的卡
-    pub fn destroy_zone<T: IntoIterator<Item = EntryId>>(
-        &mut self,
-        cards: T,
-        targeting: Targeting,
-    ) {
-        let player_id = if let Targeting::TargetPlayerSelf = targeting {
-            self.current_player.clone()
+    // 对玩家造成伤害 生命不足时强制使用RealPoint 返回true表示游戏结束
+    fn damage(&mut self, player_id: PlayerId, num: usize) -> bool {
+        let real_point = self.game_states[player_id].real_point;
+        let hp = self.game_states[player_id].hp;
+        if hp <= num {
+            warn!("{}", tr!("玩家[{}]生命值不足", player_id));
+            if real_point + hp > num {
+                info!("{}", tr!("使用RealPoint保护生命"));
+                self.game_states[player_id].hp = 1;
+                self.game_states[player_id].real_point = real_point - (num - hp + 1);
+            } else {
+                self.game_states[player_id].hp = 0;
+                self.game_states[player_id].real_point = 0;
+                return self.eliminate(player_id, GameOverReason::HpZero);
+            }
         } else {
-            self.next_player_id()
-        };
-        self.game_states[player_id].grave.extend(cards);
+            self.game_states[player_id].hp -= num;
+            info!(
+                "{}",
+                tr!("伤害后的生命值hp[{}]", Dbg(&self.game_states[player_id].hp))
+            );
+        }
+        false
+    }
+
+    // 破坏场地上的卡 卡片进入持有者的墓地
+    pub fn destroy_zone<T: IntoIterator<Item = EntryId>>(&mut self, cards: T) {
+        for card_id in cards {
+            let card = self.get_mut(card_id);
+            card.controller = card.owner;
+            let owner = card.owner;
+            self.game_states[owner].grave.push(card_id);
+        }
     }
 
     pub fn current_phase(&self) -> GamePhase {
         self.current_phase
     }
 
-    // 对手id
+    // 下一位行动的玩家 跳过已淘汰的玩家
     pub fn next_player_id(&self) -> PlayerId {
-        (self.current_player + 1) % self.players.len()
+        let count = self.players.len();
+        (1..=count)
+            .map(|offset| (self.current_player + offset) % count)
+            .find(|&id| self.is_alive(id))
+            .unwrap_or(self.current_player)
     }
 
     // 判断区域是否可以攻击
     pub fn zone_can_attack(&self, zone: &Zone) -> bool {
-        if let Zone::FrontEnd { id, cards } = zone {
-            if let Some(first) = cards.first() {
-                if let card = self.get(first.clone()) {
-                    return card.attack_counter < card.attack_max;
-                }
-            }
+        if let Zone::FrontEnd { id: _, cards } = zone
+            && let Some(first) = cards.first()
+            && let card = self.get(*first)
+        {
+            return card.attack_counter < card.attack_max;
         }
         false
     }
@@ -829,14 +1473,22 @@ impl Game {
             .collect()
     }
 
-    // 获取对手场上可以攻击的区域
+    // 获取所有对手场上可以攻击的区域
     pub fn get_attacked_zones(&self) -> Vec<Zone> {
-        self.game_states[self.next_player_id()]
+        self.opponents_of(self.current_player)
+            .into_iter()
+            .flat_map(|player_id| self.attacked_zones_of(player_id))
+            .collect()
+    }
+
+    // 获取指定玩家场上可以攻击的区域
+    pub fn attacked_zones_of(&self, player_id: PlayerId) -> Vec<Zone> {
+        self.game_states[player_id]
             .zone
             .iter()
             .filter(|&zone| {
-                if let Zone::FrontEnd { id, cards } = zone {
-                    return cards.len() > 0;
+                if let Zone::FrontEnd { id: _, cards } = zone {
+                    return !cards.is_empty();
                 }
                 false
             })
@@ -846,53 +1498,81 @@ impl Game {
 
     // 刷新卡片上的计数器
     fn flash_cards(&mut self) {
-        info!("刷新卡片的计数器");
+        info!("{}", tr!("刷新卡片的计数器"));
         for zone in self.game_states[self.current_player].zone.clone().iter() {
             match zone {
-                Zone::FrontEnd { id, cards } => {
+                Zone::FrontEnd { id: _, cards } => {
                     for entry_id in cards.iter() {
-                        let card = self.get_mut(entry_id.clone());
+                        let card = self.get_mut(*entry_id);
                         card.attack_counter = 0;
                     }
                 }
-                Zone::BackEnd { id, cards } => {
+                Zone::BackEnd { id: _, cards: _ } => {
                     // todo 这里没有攻击次数但是 有其他引用计数
                 }
             }
         }
     }
 
-    // 获取对手场上费用最高的一张卡的费用
+    // 获取所有对手场上费用最高的一张卡的费用
     fn get_highest_cost_other_zone(&self) -> usize {
-        let next_id = self.next_player_id();
         let mut all_card_ids = Vec::new();
-        for zone in &self.game_states[next_id].zone {
-            if let Zone::FrontEnd { id, cards } = zone {
-                if let Some(first) = cards.first() {
-                    all_card_ids.push(first.clone());
+        for player_id in self.opponents_of(self.current_player) {
+            for zone in &self.game_states[player_id].zone {
+                if let Zone::FrontEnd { id: _, cards } = zone
+                    && let Some(first) = cards.first()
+                {
+                    all_card_ids.push(*first);
                 }
             }
         }
 
         all_card_ids
             .iter()
-            .map(|id| self.get(id.clone()).card_info.cost)
+            .map(|id| self.get(*id).card_info.cost)
             .max()
             .unwrap_or(0)
     }
 
     // 开始游戏
     pub fn run(&mut self) {
+        self.mulligan();
+        self.emit_event(WindowEvent::Phase {
+            phase: self.current_phase,
+        });
+        self.resume();
+    }
+
+    /// 从当前阶段继续游戏 读取的存档从保存时的阶段开始
+    pub fn resume(&mut self) {
+        // 旧的存档中没有计时
+        let clock = PlayerClock::new(self.ruleset.reserve_time);
+        self.clocks.resize(self.players.len(), clock);
         loop {
-            let player = &self.players[self.current_player];
+            // 阶段事件中的效果也可能结束游戏
+            if self.game_over.is_some() || self.is_stopped() {
+                break;
+            }
+            // 每个阶段开始时通知观战者
+            self.publish_to_spectators();
+            self.poll_card_watcher();
+            // 回合玩家被淘汰时直接结束其回合
+            if !self.is_alive(self.current_player) {
+                self.phase_queue.clear();
+                self.next_phase();
+                continue;
+            }
             match self.current_phase {
                 GamePhase::Start => {
-                    info!("player[{:?}] 回合开始阶段", self.current_player);
+                    info!(
+                        "{}",
+                        tr!("player[{}] 回合开始阶段", Dbg(&self.current_player))
+                    );
                     self.flash_cards();
                     self.next_phase();
                 }
                 GamePhase::Draw => {
-                    info!("player[{:?}] 抽卡阶段", self.current_player);
+                    info!("{}", tr!("player[{}] 抽卡阶段", Dbg(&self.current_player)));
                     // todo 这里先实现简单 无事件版本的抽卡
                     self.do_effect_stacks.push_front(DoEffect::Action {
                         source: Default::default(),
@@ -905,10 +1585,14 @@ impl Game {
                     }
                     self.next_phase();
                 }
+                GamePhase::Standby => {
+                    info!("{}", tr!("player[{}] 准备阶段", Dbg(&self.current_player)));
+                    self.next_phase();
+                }
                 GamePhase::Reuse => {
-                    info!("player[{:?}] 回收阶段", self.current_player);
+                    info!("{}", tr!("player[{}] 回收阶段", Dbg(&self.current_player)));
                     let my_cost_len = self.current_cost().len();
-                    info!("Cost区长度: {:?}", my_cost_len);
+                    info!("{}", tr!("Cost区长度: {}", Dbg(&my_cost_len)));
                     if my_cost_len > 0 {
                         let highest_cost = self.get_highest_cost_other_zone();
                         let reuse_count = if highest_cost > 0 {
@@ -916,25 +1600,27 @@ impl Game {
                         } else {
                             my_cost_len
                         };
-                        info!("回收 {} 张卡片", reuse_count);
-                        let cost_cards: Vec<EntryId> = self
-                            .current_cost()
-                            .iter()
-                            .take(reuse_count)
-                            .cloned()
-                            .collect();
+                        info!("{}", tr!("回收 {} 张卡片", reuse_count));
+                        // 回收数不小于费用区长度时全部回收 否则由玩家选择
+                        let action = if reuse_count >= my_cost_len {
+                            Action::Reuse(self.current_cost())
+                        } else {
+                            Action::AskingReuse(reuse_count)
+                        };
                         self.do_effect_stacks.push_front(DoEffect::Action {
                             source: Default::default(),
                             targeting: Targeting::TargetPlayerSelf,
-                            action: Action::Reuse(cost_cards),
+                            action,
                         });
                     }
                     self.process_effect();
                     self.next_phase();
                 }
                 GamePhase::Main => {
-                    info!("player[{:?}] 主要阶段1", self.current_player);
-                    if self.is_ai_player(self.current_player) {
+                    info!("{}", tr!("player[{}] 主要阶段1", Dbg(&self.current_player)));
+                    if self.is_remote_player(self.current_player) {
+                        self.remote_read_action();
+                    } else if self.is_ai_player(self.current_player) {
                         self.ai_read_action_main();
                     } else {
                         self.help_main();
@@ -946,8 +1632,10 @@ impl Game {
                     self.next_phase();
                 }
                 GamePhase::Fight => {
-                    info!("player[{:?}] 战斗阶段", self.current_player);
-                    if self.is_ai_player(self.current_player) {
+                    info!("{}", tr!("player[{}] 战斗阶段", Dbg(&self.current_player)));
+                    if self.is_remote_player(self.current_player) {
+                        self.remote_read_action();
+                    } else if self.is_ai_player(self.current_player) {
                         self.ai_read_action_fight();
                     } else {
                         self.read_action_fight();
@@ -958,8 +1646,10 @@ impl Game {
                     self.next_phase();
                 }
                 GamePhase::Main2 => {
-                    info!("player[{:?}] 主要阶段2", self.current_player);
-                    if self.is_ai_player(self.current_player) {
+                    info!("{}", tr!("player[{}] 主要阶段2", Dbg(&self.current_player)));
+                    if self.is_remote_player(self.current_player) {
+                        self.remote_read_action();
+                    } else if self.is_ai_player(self.current_player) {
                         self.ai_read_action_main();
                     } else {
                         self.help_main();
@@ -971,8 +1661,11 @@ impl Game {
                     self.next_phase();
                 }
                 GamePhase::End => {
-                    info!("player[{:?}] 回合结束阶段", self.current_player);
-                    info!("========== 回合结束 ==========");
+                    info!(
+                        "{}",
+                        tr!("player[{}] 回合结束阶段", Dbg(&self.current_player))
+                    );
+                    info!("{}", tr!("========== 回合结束 =========="));
                     for (i, state) in self.game_states.iter().enumerate() {
                         let front_end_count: usize = state
                             .zone
@@ -997,46 +1690,68 @@ impl Game {
                             })
                             .sum();
                         info!(
-                            "玩家[{}] HP:{} RealPoint:{} 手卡:{} 前场:{} 后场:{} 卡组:{} 墓地:{}",
-                            i,
-                            state.hp,
-                            state.real_point,
-                            state.hand.len(),
-                            front_end_count,
-                            back_end_count,
-                            state.desk.len(),
-                            state.grave.len()
+                            "{}",
+                            tr!(
+                                "玩家[{}] HP:{} RealPoint:{} 手卡:{} 前场:{} 后场:{} 卡组:{} 墓地:{}",
+                                i,
+                                state.hp,
+                                state.real_point,
+                                state.hand.len(),
+                                front_end_count,
+                                back_end_count,
+                                state.desk.len(),
+                                state.grave.len()
+                            )
                         );
                     }
-                    info!("==============================");
+                    info!("{}", tr!("=============================="));
                     self.next_phase();
-                    self.current_player = self.next_player_id();
                 }
             }
         }
 
+        self.publish_to_spectators();
         // 输出游戏结果
-        if let Some((loser_id, reason)) = &self.game_over {
-            let winner_id = (*loser_id + 1) % self.players.len();
-            let reason_text = match reason {
-                GameOverReason::DeckOut => "卡组耗尽，无法抽卡",
-                GameOverReason::HpZero => "生命值归零",
-            };
-            info!("========== 游戏结束 ==========");
-            info!("败者: 玩家[{}], 原因: {}", loser_id, reason_text);
-            info!("胜者: 玩家[{}]", winner_id);
-            info!("==============================");
+        if self.game_over.is_some() {
+            self.remote_game_over();
+            info!("{}", tr!("========== 游戏结束 =========="));
+            for (loser_id, reason) in &self.eliminated {
+                let reason_text = match reason {
+                    GameOverReason::DeckOut => tr!("卡组耗尽，无法抽卡"),
+                    GameOverReason::HpZero => tr!("生命值归零"),
+                    GameOverReason::Timeout => tr!("超时次数达到上限"),
+                };
+                info!("{}", tr!("败者: 玩家[{}], 原因: {}", loser_id, reason_text));
+            }
+            let winners = self.alive_players();
+            if let Some(&winner_id) = winners.first() {
+                if self.ruleset.teams > 0 {
+                    info!(
+                        "{}",
+                        tr!(
+                            "胜者: 队伍[{}] 玩家{}",
+                            self.ruleset.team_of(winner_id),
+                            Dbg(&winners)
+                        )
+                    );
+                } else {
+                    info!("{}", tr!("胜者: 玩家[{}]", winner_id));
+                }
+            }
+            info!("{}", tr!("=============================="));
         }
     }
 }
 
 /// 游戏阶段
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GamePhase {
     // 回合开始阶段
     Start,
     // 抽卡阶段
     Draw,
+    // 准备阶段
+    Standby,
     // 回收阶段
     Reuse,
     // 主要阶段
@@ -1050,29 +1765,34 @@ pub enum GamePhase {
 }
 
 impl GamePhase {
-    pub fn next(phase: &GamePhase) -> GamePhase {
-        match phase {
-            GamePhase::Start => GamePhase::Draw,
-            GamePhase::Draw => GamePhase::Reuse,
-            GamePhase::Reuse => GamePhase::Main,
-            GamePhase::Main => GamePhase::Fight,
-            GamePhase::Fight => GamePhase::Main2,
-            GamePhase::Main2 => GamePhase::End,
-            GamePhase::End => GamePhase::Start,
+    // 规则文件和脚本中使用的阶段名称
+    pub fn from_name(name: &str) -> Option<GamePhase> {
+        match name {
+            "start" => Some(GamePhase::Start),
+            "draw" => Some(GamePhase::Draw),
+            "standby" => Some(GamePhase::Standby),
+            "reuse" => Some(GamePhase::Reuse),
+            "main" => Some(GamePhase::Main),
+            "fight" => Some(GamePhase::Fight),
+            "main2" => Some(GamePhase::Main2),
+            "end" => Some(GamePhase::End),
+            _ => None,
         }
     }
 }
 
 /// 游戏结束原因
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum GameOverReason {
     /// 卡组没有卡时抽卡
     DeckOut,
     /// 生命值小于等于0
     HpZero,
+    /// 超时次数达到规则的上限
+    Timeout,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameState {
     player_id: PlayerId,
     // 生命值
@@ -1092,40 +1812,44 @@ pub struct GameState {
 }
 
 impl GameState {
-    fn new(player_id: PlayerId, cards: Vec<Card>, id_generator: &mut IdGenerator) -> Self {
+    fn new(
+        player_id: PlayerId,
+        cards: Vec<Card>,
+        id_generator: &mut IdGenerator,
+        ruleset: &Ruleset,
+    ) -> Self {
         GameState {
             player_id,
-            hp: 6,
+            hp: ruleset.start_hp,
             real_point: 0,
             desk: cards.iter().map(|cards| cards.entry_id).collect(),
             hand: Vec::new(),
             cost: Vec::new(),
             grave: Vec::new(),
-            zone: Zone::init(id_generator),
+            zone: Zone::init(id_generator, ruleset),
         }
     }
 
-    pub fn player_id(&self) -> PlayerId {
-        self.player_id
-    }
-
     // 洗牌算法
-    pub fn shuffle(&mut self) {
-        self.desk.shuffle(&mut thread_rng());
+    pub fn shuffle<R: Rng>(&mut self, rng: &mut R) {
+        self.desk.shuffle(rng);
     }
 
     // 抽卡 返回true表示卡组耗尽
-    pub fn draw(&mut self, num: usize) -> bool {
+    pub fn draw(&mut self, num: usize, ruleset: &Ruleset) -> bool {
         for _ in 0..num {
             if let Some(entry_id) = self.desk.pop() {
-                if self.hand.len() >= MAX_HAND_SIZE {
+                if self.hand.len() >= ruleset.max_hand_size {
                     self.grave.push(entry_id);
-                    warn!("手卡已满[{}]，卡片进入墓地", MAX_HAND_SIZE);
+                    warn!(
+                        "{}",
+                        tr!("手卡已满[{}]，卡片进入墓地", ruleset.max_hand_size)
+                    );
                 } else {
                     self.hand.push(entry_id);
                 }
             } else {
-                warn!("卡组耗尽，无法抽卡");
+                warn!("{}", tr!("卡组耗尽，无法抽卡"));
                 return true;
             }
         }
@@ -1134,7 +1858,7 @@ impl GameState {
 }
 
 /// 场地
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Zone {
     //前场
     FrontEnd {
@@ -1150,17 +1874,17 @@ pub enum Zone {
 }
 
 impl Zone {
-    pub fn init(id_generator: &mut IdGenerator) -> Vec<Zone> {
+    pub fn init(id_generator: &mut IdGenerator, ruleset: &Ruleset) -> Vec<Zone> {
         // 初始化一块场地信息
         let mut ret = Vec::new();
-        // 前场5个 后场5个
-        for _ in 0..4 {
+        // 前场后场数量由规则决定
+        for _ in 0..ruleset.front_zone_count {
             ret.push(Zone::FrontEnd {
                 id: id_generator.next(),
                 cards: Vec::new(),
             });
         }
-        for _ in 0..4 {
+        for _ in 0..ruleset.back_zone_count {
             ret.push(Zone::BackEnd {
                 id: id_generator.next(),
                 cards: Vec::new(),
@@ -1172,16 +1896,16 @@ impl Zone {
     // 是否包含id
     pub fn has_id(&self, zone_id: EntryId) -> bool {
         match self {
-            Zone::FrontEnd { id, cards } => id.clone() == zone_id,
-            Zone::BackEnd { id, cards } => id.clone() == zone_id,
+            Zone::FrontEnd { id, cards: _ } => *id == zone_id,
+            Zone::BackEnd { id, cards: _ } => *id == zone_id,
         }
     }
 
     // 是否有卡片
     pub fn has_cards(&self) -> bool {
         match self {
-            Zone::FrontEnd { id, cards } => cards.len() > 0,
-            Zone::BackEnd { id, cards } => cards.len() > 0,
+            Zone::FrontEnd { id: _, cards } => !cards.is_empty(),
+            Zone::BackEnd { id: _, cards } => !cards.is_empty(),
         }
     }
 }
@@ -1190,37 +1914,41 @@ impl Zone {
 mod tests {
     use super::*;
     use crate::card::CardInfoBuilder;
+    use crate::deck_check::DeckViolation;
     use crate::player::PlayerDesk;
+    use crate::test_util::{test_lua_api, test_players};
 
-    fn test_lua_api() -> LuaApi {
+    // 执行卡片脚本得到的 LuaApi
+    fn script_api(code: &str) -> LuaApi {
+        let lua = mlua::Lua::new();
         let mut api = LuaApi::new();
-        let card_info = CardInfoBuilder::new("test-card".to_string()).build();
-        api.cards.insert("test-card".to_string(), card_info);
+        LuaApi::install(&lua).unwrap();
+        api.load_with(&lua, || lua.load(code).exec()).unwrap();
         api
     }
 
     fn test_game(desk_size: usize) -> Game {
-        let desk = PlayerDesk(vec!["test-card".to_string(); desk_size]);
-        let players = vec![
-            Player {
-                id: 0,
-                player_desk: desk.clone(),
-            },
-            Player {
-                id: 1,
-                player_desk: desk.clone(),
-            },
-        ];
-        let api = test_lua_api();
-        Game::new(players, &api, HashSet::new())
+        let players = test_players(2, desk_size);
+        Game::new(players, &test_lua_api(), HashSet::new(), Ruleset::default()).unwrap()
+    }
+
+    impl Game {
+        fn first_zone_id(&self) -> EntryId {
+            match &self.game_states[self.current_player].zone[0] {
+                Zone::FrontEnd { id, .. } | Zone::BackEnd { id, .. } => *id,
+            }
+        }
     }
 
     fn test_card(id_gen: &mut IdGenerator) -> Card {
         Card {
             entry_id: id_gen.next(),
             card_info: CardInfoBuilder::new("test".to_string()).build(),
+            owner: 0,
+            controller: 0,
             attack_counter: 0,
             attack_max: 1,
+            face_down: false,
         }
     }
 
@@ -1230,8 +1958,8 @@ mod tests {
     fn test_draw_success() {
         let mut id_gen = IdGenerator::new();
         let cards = vec![test_card(&mut id_gen)];
-        let mut state = GameState::new(0, cards, &mut id_gen);
-        assert!(!state.draw(1));
+        let mut state = GameState::new(0, cards, &mut id_gen, &Ruleset::default());
+        assert!(!state.draw(1, &Ruleset::default()));
         assert_eq!(state.hand.len(), 1);
         assert_eq!(state.desk.len(), 0);
     }
@@ -1239,8 +1967,8 @@ mod tests {
     #[test]
     fn test_draw_empty_deck_returns_true() {
         let mut id_gen = IdGenerator::new();
-        let mut state = GameState::new(0, vec![], &mut id_gen);
-        assert!(state.draw(1));
+        let mut state = GameState::new(0, vec![], &mut id_gen, &Ruleset::default());
+        assert!(state.draw(1, &Ruleset::default()));
         assert_eq!(state.hand.len(), 0);
     }
 
@@ -1248,12 +1976,643 @@ mod tests {
     fn test_draw_partial_deck_out() {
         let mut id_gen = IdGenerator::new();
         let cards = vec![test_card(&mut id_gen)];
-        let mut state = GameState::new(0, cards, &mut id_gen);
+        let mut state = GameState::new(0, cards, &mut id_gen, &Ruleset::default());
         // 卡组只有1张，抽2张，抽到第2张时耗尽
-        assert!(state.draw(2));
+        assert!(state.draw(2, &Ruleset::default()));
         assert_eq!(state.hand.len(), 1); // 成功抽到1张
     }
 
+    // === 规则配置 ===
+
+    #[test]
+    fn test_game_uses_ruleset() {
+        let players = test_players(1, 10);
+        let mut ruleset = Ruleset::new("variant".to_string());
+        ruleset.start_hp = 3;
+        ruleset.opening_hand = 7;
+        ruleset.front_zone_count = 2;
+        ruleset.back_zone_count = 1;
+        let game = Game::new(players, &test_lua_api(), HashSet::new(), ruleset).unwrap();
+        assert_eq!(game.game_states[game.current_player].hp, 3);
+        assert_eq!(game.current_hand().len(), 7);
+        assert_eq!(game.current_zone().len(), 3);
+    }
+
+    #[test]
+    fn test_draw_respects_max_hand_size() {
+        let mut id_gen = IdGenerator::new();
+        let cards = vec![test_card(&mut id_gen), test_card(&mut id_gen)];
+        let ruleset = Ruleset {
+            max_hand_size: 1,
+            ..Default::default()
+        };
+        let mut state = GameState::new(0, cards, &mut id_gen, &ruleset);
+        assert!(!state.draw(2, &ruleset));
+        assert_eq!(state.hand.len(), 1);
+        assert_eq!(state.grave.len(), 1);
+    }
+
+    // === 阶段结构 ===
+
+    #[test]
+    fn test_phase_order_follows_ruleset() {
+        let players = test_players(2, 10);
+        let mut ruleset = Ruleset::default();
+        ruleset
+            .set_phases("phases", &["start", "draw", "standby", "end"])
+            .unwrap();
+        ruleset.set_phases("skip_first_turn", &["draw"]).unwrap();
+        let mut game = Game::new(players, &test_lua_api(), HashSet::new(), ruleset).unwrap();
+        assert_eq!(game.current_phase(), GamePhase::Start);
+        game.next_phase();
+        assert_eq!(game.current_phase(), GamePhase::Standby);
+        game.next_phase();
+        assert_eq!(game.current_phase(), GamePhase::End);
+        let first = game.current_player();
+        game.next_phase();
+        assert_eq!(game.current_phase(), GamePhase::Start);
+        assert_eq!(game.current_player(), (first + 1) % 2);
+        assert_eq!(game.turn_count(), 2);
+        // 第二回合不再跳过抽卡
+        game.next_phase();
+        assert_eq!(game.current_phase(), GamePhase::Draw);
+    }
+
+    #[test]
+    fn test_effect_skips_and_adds_phase() {
+        let mut game = test_game(10);
+        game.do_effect_stacks.push_back(DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::None,
+            action: Action::SkipPhase(GamePhase::Fight),
+        });
+        game.do_effect_stacks.push_back(DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::None,
+            action: Action::AddPhase(GamePhase::Standby),
+        });
+        game.process_effect();
+        assert!(!game.phase_queue.contains(&GamePhase::Fight));
+        game.next_phase();
+        assert_eq!(game.current_phase(), GamePhase::Standby);
+        game.next_phase();
+        assert_eq!(game.current_phase(), GamePhase::Draw);
+    }
+
+    #[test]
+    fn test_phase_event_triggers_field_card() {
+        let api = script_api(
+            r#"
+            define_card("phase-card", function(card)
+                card:reg_effect("e1", function(effect)
+                    effect:window("phase:standby")
+                    effect:draw(1)
+                end)
+            end)
+            "#,
+        );
+        let desk = PlayerDesk(vec!["phase-card".to_string(); 10]);
+        let players = vec![Player {
+            id: 0,
+            player_desk: desk,
+        }];
+        let mut game = Game::new(players, &api, HashSet::new(), Ruleset::default()).unwrap();
+        let card = game.current_hand()[0];
+        game.do_effect_stacks.push_front(DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::None,
+            action: Action::Set {
+                card_id: card,
+                zone_id: game.first_zone_id(),
+            },
+        });
+        game.process_effect();
+        let hand_len = game.current_hand().len();
+        game.add_phase(GamePhase::Standby);
+        game.next_phase();
+        assert_eq!(game.current_hand().len(), hand_len + 1);
+    }
+
+    #[test]
+    fn test_game_rejects_illegal_deck() {
+        let ruleset = Ruleset {
+            min_deck_size: 30,
+            ..Default::default()
+        };
+        let Err(SetupError::Deck(err)) = Game::new(
+            test_players(2, 20),
+            &test_lua_api(),
+            HashSet::new(),
+            ruleset,
+        ) else {
+            panic!("卡组应该不符合规则");
+        };
+        assert_eq!(err.player_id, 0);
+        assert_eq!(
+            err.violations,
+            vec![DeckViolation::TooFew { size: 20, min: 30 }]
+        );
+    }
+
+    // === 先手和起手调度 ===
+
+    #[test]
+    fn test_same_seed_same_setup() {
+        let api = test_lua_api();
+        let a = Game::new_with_seed(
+            test_players(2, 20),
+            &api,
+            HashSet::new(),
+            Ruleset::default(),
+            7,
+        )
+        .unwrap();
+        let b = Game::new_with_seed(
+            test_players(2, 20),
+            &api,
+            HashSet::new(),
+            Ruleset::default(),
+            7,
+        )
+        .unwrap();
+        assert_eq!(a.current_player(), b.current_player());
+        assert_eq!(a.game_states[0].desk, b.game_states[0].desk);
+        assert_eq!(a.hand(1), b.hand(1));
+    }
+
+    #[test]
+    fn test_first_player_varies_with_seed() {
+        let api = test_lua_api();
+        let firsts: HashSet<PlayerId> = (0..32)
+            .map(|seed| {
+                Game::new_with_seed(
+                    test_players(2, 20),
+                    &api,
+                    HashSet::new(),
+                    Ruleset::default(),
+                    seed,
+                )
+                .unwrap()
+                .current_player()
+            })
+            .collect();
+        assert_eq!(firsts.len(), 2);
+    }
+
+    #[test]
+    fn test_redraw_returns_cards_to_deck() {
+        let mut game = test_game(20);
+        let hand = game.hand(0);
+        let desk_len = game.game_states[0].desk.len();
+        let all_cards = |game: &Game| {
+            let state = &game.game_states[0];
+            let mut cards: Vec<EntryId> = state.desk.iter().chain(&state.hand).copied().collect();
+            cards.sort();
+            cards
+        };
+        let before = all_cards(&game);
+        // 不在手卡中的卡会被忽略
+        game.redraw(0, vec![hand[0], hand[1], 9999]);
+        assert_eq!(game.hand(0).len(), hand.len());
+        assert_eq!(game.game_states[0].desk.len(), desk_len);
+        assert_eq!(all_cards(&game), before);
+    }
+
+    #[test]
+    fn test_ai_mulligan_keeps_cheap_hand() {
+        let api = test_lua_api();
+        let ai_players: HashSet<PlayerId> = [0, 1].into_iter().collect();
+        let mut game =
+            Game::new(test_players(2, 20), &api, ai_players, Ruleset::default()).unwrap();
+        let hands = (game.hand(0), game.hand(1));
+        game.mulligan();
+        assert_eq!((game.hand(0), game.hand(1)), hands);
+    }
+
+    // === 回收 ===
+
+    #[test]
+    fn test_check_reuse_choice() {
+        let mut game = test_game(10);
+        let hand = game.hand(0);
+        game.game_states[0].cost.extend(&hand[..3]);
+        let choice = ChoiceReq::Reuse {
+            player_id: 0,
+            limit: 2,
+        };
+        let check = |cards: Vec<EntryId>| game.check_choice(&choice, &ChoiceRes::Reuse(cards));
+        assert_eq!(check(vec![hand[2], hand[0]]), Ok(()));
+        assert_eq!(
+            check(vec![hand[0]]),
+            Err(ChoiceError::WrongCount { need: 2, got: 1 })
+        );
+        assert_eq!(
+            check(vec![hand[0], hand[0]]),
+            Err(ChoiceError::Duplicate(hand[0]))
+        );
+        assert_eq!(
+            check(vec![hand[0], hand[4]]),
+            Err(ChoiceError::NotInCost(hand[4]))
+        );
+        assert_eq!(
+            game.check_choice(&choice, &ChoiceRes::None),
+            Err(ChoiceError::WrongAnswer)
+        );
+    }
+
+    // === 时间限制 ===
+
+    #[test]
+    fn test_timeout_choice_pays_cost() {
+        let mut game = test_game(20);
+        let current = game.current_player;
+        let hand = game.hand(current);
+        let card = hand[0];
+        game.get_mut(card).card_info.cost = 3;
+        game.game_states[current].hand.retain(|&x| x != card);
+        let choice = ChoiceReq::Cost(card);
+        let res = game.timeout_choice(&choice);
+        assert_eq!(
+            res,
+            ChoiceRes::Cost {
+                hands: hand[1..4].to_vec(),
+                real_point: 0,
+            }
+        );
+        assert_eq!(game.check_choice(&choice, &res), Ok(()));
+        // Cost区只剩1个位置 剩下的用RealPoint支付
+        game.game_states[current]
+            .cost
+            .extend([100, 101, 102, 103, 104]);
+        assert_eq!(game.timeout_choice(&choice), ChoiceRes::None);
+        game.game_states[current].real_point = 2;
+        assert_eq!(
+            game.timeout_choice(&choice),
+            ChoiceRes::Cost {
+                hands: vec![hand[1]],
+                real_point: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_silent_remote_player_loses_on_time() {
+        use crate::remote::RemoteSeat;
+        use std::io::{self, BufRead, BufReader};
+
+        let ruleset = Ruleset {
+            decision_time: 1,
+            max_timeouts: 2,
+            ..Default::default()
+        };
+        let ai_players: HashSet<PlayerId> = [1].into_iter().collect();
+        let mut game =
+            Game::new(test_players(2, 20), &test_lua_api(), ai_players, ruleset).unwrap();
+        // 不回复的客户端 只读取请求
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, reply_writer) = io::pipe().unwrap();
+        game.set_remote_player(
+            0,
+            RemoteSeat::new(BufReader::new(reply_reader), request_writer),
+        );
+        let client = std::thread::spawn(move || BufReader::new(request_reader).lines().count());
+        game.run();
+        assert_eq!(game.clocks[0].timeouts, 2);
+        let (loser, reason) = game.game_over.as_ref().unwrap();
+        assert_eq!(*loser, 0);
+        assert!(matches!(reason, GameOverReason::Timeout));
+        drop(game);
+        drop(reply_writer);
+        assert!(client.join().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_turn_time_expires_once_per_turn() {
+        use crate::remote::RemoteSeat;
+        use std::io::{self, BufRead, BufReader};
+
+        let ruleset = Ruleset {
+            turn_time: 1,
+            max_timeouts: 2,
+            ..Default::default()
+        };
+        let mut game = Game::new(
+            test_players(2, 20),
+            &test_lua_api(),
+            HashSet::new(),
+            ruleset,
+        )
+        .unwrap();
+        game.clocks = vec![PlayerClock::new(0); 2];
+        let current = game.current_player;
+        // 不回复的客户端 记录收到的操作请求数
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, reply_writer) = io::pipe().unwrap();
+        game.set_remote_player(
+            current,
+            RemoteSeat::new(BufReader::new(reply_reader), request_writer),
+        );
+        let client = std::thread::spawn(move || {
+            BufReader::new(request_reader)
+                .lines()
+                .filter(|line| line.as_ref().unwrap().contains(r#""type":"action""#))
+                .count()
+        });
+        game.current_phase = GamePhase::Main;
+        game.remote_read_action();
+        assert_eq!(game.clocks[current].timeouts, 1);
+        // 本回合的时间已经用完 之后的阶段和选择不再询问 也不再记录超时
+        let start = Instant::now();
+        for phase in [GamePhase::Fight, GamePhase::Main2] {
+            game.current_phase = phase;
+            game.remote_read_action();
+        }
+        assert_eq!(
+            game.ask_choice(current, ChoiceReq::FightDamage),
+            ChoiceRes::None
+        );
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(game.clocks[current].timeouts, 1);
+        assert!(game.game_over.is_none());
+        drop(game);
+        drop(reply_writer);
+        assert_eq!(client.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rejected_actions_keep_the_deadline() {
+        use crate::remote::{ClientMessage, RemoteSeat, ServerMessage};
+        use std::io::{self, BufRead, BufReader, Write};
+
+        let ruleset = Ruleset {
+            decision_time: 1,
+            ..Default::default()
+        };
+        let mut game = Game::new(
+            test_players(2, 20),
+            &test_lua_api(),
+            HashSet::new(),
+            ruleset,
+        )
+        .unwrap();
+        game.clocks = vec![PlayerClock::new(0); 2];
+        let current = game.current_player;
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, mut reply_writer) = io::pipe().unwrap();
+        game.set_remote_player(
+            current,
+            RemoteSeat::new(BufReader::new(reply_reader), request_writer),
+        );
+        // 每次询问都在时限内回复一个不能执行的操作 游戏结束后的回复可能写入失败
+        let client = std::thread::spawn(move || {
+            for line in BufReader::new(request_reader).lines() {
+                if let ServerMessage::Action { .. } = serde_json::from_str(&line.unwrap()).unwrap()
+                {
+                    std::thread::sleep(Duration::from_millis(300));
+                    let action = PlayerAction::EffectCard { card_id: 0 };
+                    serde_json::to_writer(&mut reply_writer, &ClientMessage::Action { action })
+                        .ok();
+                    writeln!(reply_writer).ok();
+                }
+            }
+        });
+        game.current_phase = GamePhase::Main;
+        let start = Instant::now();
+        game.remote_read_action();
+        // 被拒绝的操作不重新计时
+        assert_eq!(game.clocks[current].timeouts, 1);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        drop(game);
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn test_asking_reuse_moves_chosen_cards() {
+        let api = test_lua_api();
+        let ai_players: HashSet<PlayerId> = [0, 1].into_iter().collect();
+        let mut game =
+            Game::new(test_players(2, 20), &api, ai_players, Ruleset::default()).unwrap();
+        let current = game.current_player;
+        let hand = game.hand(current);
+        game.game_states[current]
+            .hand
+            .retain(|card| !hand[..3].contains(card));
+        game.game_states[current].cost.extend(&hand[..3]);
+        game.do_effect_stacks.push_front(DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::TargetPlayerSelf,
+            action: Action::AskingReuse(2),
+        });
+        game.process_effect();
+        assert_eq!(game.cost_zone(current).len(), 1);
+        assert_eq!(game.hand(current).len(), hand.len() - 1);
+    }
+
+    // === 选择检查 ===
+
+    fn cost_card_api() -> LuaApi {
+        script_api(r#"define_card("cost-3", function(card) card:cost(3) end)"#)
+    }
+
+    #[test]
+    fn test_check_cost_choice() {
+        let desk = PlayerDesk(vec!["cost-3".to_string(); 10]);
+        let players = vec![Player {
+            id: 0,
+            player_desk: desk,
+        }];
+        let mut game = Game::new(
+            players,
+            &cost_card_api(),
+            HashSet::new(),
+            Ruleset::default(),
+        )
+        .unwrap();
+        let hand = game.hand(0);
+        let choice = ChoiceReq::Cost(hand[0]);
+        game.game_states[0].hand.retain(|&card| card != hand[0]);
+        game.game_states[0].real_point = 1;
+        let check = |hands: Vec<EntryId>, real_point: usize| {
+            game.check_choice(&choice, &ChoiceRes::Cost { hands, real_point })
+        };
+        assert_eq!(check(vec![hand[1], hand[2]], 1), Ok(()));
+        assert_eq!(check(vec![hand[1], hand[2], hand[3]], 0), Ok(()));
+        assert_eq!(
+            check(vec![hand[1]], 2),
+            Err(ChoiceError::NotEnoughRealPoint { need: 2, have: 1 })
+        );
+        assert_eq!(
+            check(vec![hand[1]], 1),
+            Err(ChoiceError::WrongCost { need: 3, paid: 2 })
+        );
+        // 登场的卡本身已经离开手卡
+        assert_eq!(
+            check(vec![hand[0], hand[1]], 1),
+            Err(ChoiceError::NotInHand(hand[0]))
+        );
+        assert_eq!(game.check_choice(&choice, &ChoiceRes::None), Ok(()));
+    }
+
+    #[test]
+    fn test_check_cost_choice_full_cost_zone() {
+        let desk = PlayerDesk(vec!["cost-3".to_string(); 10]);
+        let players = vec![Player {
+            id: 0,
+            player_desk: desk,
+        }];
+        let ruleset = Ruleset {
+            max_cost_size: 1,
+            ..Default::default()
+        };
+        let mut game = Game::new(players, &cost_card_api(), HashSet::new(), ruleset).unwrap();
+        let hand = game.hand(0);
+        game.game_states[0].real_point = 2;
+        let res = ChoiceRes::Cost {
+            hands: vec![hand[1], hand[2]],
+            real_point: 1,
+        };
+        assert_eq!(
+            game.check_choice(&ChoiceReq::Cost(hand[0]), &res),
+            Err(ChoiceError::CostZoneFull { slots: 1, hands: 2 })
+        );
+    }
+
+    #[test]
+    fn test_cost_rejects_invalid_payment() {
+        let mut game = test_game(10);
+        let current = game.current_player;
+        let hand = game.hand(current);
+        let other = game.hand(game.opponent_of(current).unwrap())[0];
+        game.game_states[current].real_point = 1;
+        assert_eq!(
+            game.cost(vec![hand[0]], 2),
+            Err(ChoiceError::NotEnoughRealPoint { need: 2, have: 1 })
+        );
+        assert_eq!(
+            game.cost(vec![hand[0], other], 0),
+            Err(ChoiceError::NotInHand(other))
+        );
+        assert_eq!(
+            game.cost(vec![hand[0], hand[0]], 0),
+            Err(ChoiceError::Duplicate(hand[0]))
+        );
+        // 不合法的支付不改变状态
+        assert_eq!(game.hand(current), hand);
+        assert!(game.cost_zone(current).is_empty());
+        assert_eq!(game.real_point(current), 1);
+
+        assert_eq!(game.cost(vec![hand[0]], 1), Ok(()));
+        assert_eq!(game.cost_zone(current), vec![hand[0]]);
+        assert_eq!(game.hand(current).len(), hand.len() - 1);
+        assert_eq!(game.real_point(current), 0);
+    }
+
+    #[test]
+    fn test_check_fight_damage_choice() {
+        let mut game = test_game(10);
+        let current = game.current_player;
+        game.game_states[current].real_point = 2;
+        let check = |res: ChoiceRes| game.check_choice(&ChoiceReq::FightDamage, &res);
+        assert_eq!(check(ChoiceRes::FightDamageByRealPoint(2)), Ok(()));
+        assert_eq!(check(ChoiceRes::None), Ok(()));
+        assert_eq!(check(ChoiceRes::FightDamageByRealPoint(0)), Ok(()));
+        assert_eq!(
+            check(ChoiceRes::FightDamageByRealPoint(3)),
+            Err(ChoiceError::NotEnoughRealPoint { need: 3, have: 2 })
+        );
+    }
+
+    // === 持有者和控制者 ===
+
+    fn trigger_card_game() -> Game {
+        let api = script_api(
+            r#"
+            define_card("watcher", function(card)
+                card:reg_effect("e1", function(effect)
+                    effect:window("opponent_start")
+                    effect:draw(1)
+                end)
+                card:reg_effect("e2", function(effect)
+                    effect:window("opponent_start")
+                    effect:damage(2)
+                end)
+            end)
+            "#,
+        );
+        let desk = PlayerDesk(vec!["watcher".to_string(); 10]);
+        let players = vec![
+            Player {
+                id: 0,
+                player_desk: desk.clone(),
+            },
+            Player {
+                id: 1,
+                player_desk: desk,
+            },
+        ];
+        Game::new(players, &api, HashSet::new(), Ruleset::default()).unwrap()
+    }
+
+    #[test]
+    fn test_opponent_start_effect_resolves_for_controller() {
+        let mut game = trigger_card_game();
+        let watcher_player = game.current_player;
+        let turn_player = game.opponent_of(watcher_player).unwrap();
+        let card = game.hand(watcher_player)[0];
+        game.do_effect_stacks.push_front(DoEffect::Action {
+            source: Targeting::TargetPlayerSelf,
+            targeting: Targeting::None,
+            action: Action::Set {
+                card_id: card,
+                zone_id: game.first_zone_id(),
+            },
+        });
+        game.process_effect();
+        assert_eq!(game.get(card).controller, watcher_player);
+        let hand_len = game.hand(watcher_player).len();
+        let turn_hand_len = game.hand(turn_player).len();
+        // 进入对手的回合开始阶段
+        game.current_player = turn_player;
+        game.emit_event(WindowEvent::Phase {
+            phase: GamePhase::Start,
+        });
+        assert_eq!(game.hand(watcher_player).len(), hand_len + 1);
+        assert_eq!(game.hand(turn_player).len(), turn_hand_len);
+        assert_eq!(game.game_states[turn_player].hp, 4);
+        assert_eq!(game.game_states[watcher_player].hp, 6);
+    }
+
+    #[test]
+    fn test_destroyed_card_goes_to_owner_grave() {
+        let mut game = trigger_card_game();
+        let owner = game.current_player;
+        let other = game.opponent_of(owner).unwrap();
+        let card = game.hand(owner)[0];
+        game.game_states[owner].hand.retain(|&x| x != card);
+        // 卡片被对手控制并放在对手场上
+        let zone_id = match &game.game_states[other].zone[0] {
+            Zone::FrontEnd { id, .. } | Zone::BackEnd { id, .. } => *id,
+        };
+        game.do_effect_stacks.push_front(DoEffect::Action {
+            source: Targeting::TargetZone(zone_id),
+            targeting: Targeting::None,
+            action: Action::Set {
+                card_id: card,
+                zone_id,
+            },
+        });
+        game.process_effect();
+        assert_eq!(game.get(card).controller, other);
+        game.do_effect_stacks.push_front(DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::None,
+            action: Action::FightDestroy { zone_id },
+        });
+        game.process_effect();
+        assert!(game.game_states[owner].grave.contains(&card));
+        assert_eq!(game.get(card).controller, owner);
+    }
+
     // === 卡组耗尽 游戏结束判定 ===
 
     #[test]
@@ -1443,4 +2802,295 @@ mod tests {
         // AddRealPoint 不应该被执行
         assert_eq!(game.game_states[current].real_point, 0);
     }
+
+    // === 组合效果 ===
+
+    fn add_phase(phase: GamePhase) -> DoEffect {
+        DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::None,
+            action: Action::AddPhase(phase),
+        }
+    }
+
+    #[test]
+    fn test_and_action_runs_before_pending_effects() {
+        let mut game = test_game(10);
+        game.phase_queue.clear();
+        game.do_effect_stacks.push_back(DoEffect::AndAction(vec![
+            add_phase(GamePhase::Draw),
+            add_phase(GamePhase::Standby),
+        ]));
+        game.do_effect_stacks.push_back(add_phase(GamePhase::End));
+        game.process_effect();
+        // 追加的阶段排在前面 后执行的在最前
+        assert_eq!(
+            Vec::from(game.phase_queue.clone()),
+            vec![GamePhase::End, GamePhase::Standby, GamePhase::Draw]
+        );
+    }
+
+    #[test]
+    fn test_or_action_runs_chosen_effect() {
+        let mut game = test_game(10);
+        game.ai_players = HashSet::from([0, 1]);
+        game.phase_queue.clear();
+        let effects = vec![add_phase(GamePhase::Draw), add_phase(GamePhase::Standby)];
+        let choice = ChoiceReq::OrAction(effects.clone());
+        assert_eq!(game.check_choice(&choice, &ChoiceRes::OrAction(1)), Ok(()));
+        assert_eq!(
+            game.check_choice(&choice, &ChoiceRes::OrAction(2)),
+            Err(ChoiceError::NoOption { index: 2, count: 2 })
+        );
+
+        // AI执行第一项
+        game.do_effect_stacks.push_back(DoEffect::OrAction(effects));
+        game.process_effect();
+        assert_eq!(Vec::from(game.phase_queue.clone()), vec![GamePhase::Draw]);
+    }
+
+    // === 多人对局 ===
+
+    fn multi_player_game(count: usize, teams: usize) -> Game {
+        let players = test_players(count, 20);
+        let ruleset = Ruleset {
+            teams,
+            ..Default::default()
+        };
+        let mut game =
+            Game::new_with_seed(players, &test_lua_api(), HashSet::new(), ruleset, 1).unwrap();
+        game.current_player = 0;
+        game
+    }
+
+    fn kill(game: &mut Game, player_id: PlayerId) {
+        game.game_states[player_id].hp = 1;
+        game.do_effect_stacks.push_front(DoEffect::Action {
+            source: Targeting::None,
+            targeting: Targeting::TargetPlayer(player_id),
+            action: Action::Damage(5),
+        });
+        game.process_effect();
+    }
+
+    #[test]
+    fn test_free_for_all_elimination() {
+        let mut game = multi_player_game(3, 0);
+        assert_eq!(game.opponents_of(0), vec![1, 2]);
+
+        kill(&mut game, 1);
+        assert!(game.game_over.is_none());
+        assert!(!game.is_alive(1));
+        // 轮到下一位时跳过被淘汰的玩家
+        assert_eq!(game.next_player_id(), 2);
+        assert_eq!(game.opponents_of(0), vec![2]);
+
+        kill(&mut game, 2);
+        let (loser, _) = game.game_over.clone().unwrap();
+        assert_eq!(loser, 2);
+        assert_eq!(game.alive_players(), vec![0]);
+        // 没有对手时不会选到其他玩家
+        assert_eq!(game.opponent_of(0), None);
+    }
+
+    #[test]
+    fn test_team_count_must_fit_players() {
+        for (count, teams) in [(2, 2), (3, 3), (4, 5), (3, 1)] {
+            let players = test_players(count, 20);
+            let ruleset = Ruleset {
+                teams,
+                ..Default::default()
+            };
+            let err = Game::new(players, &test_lua_api(), HashSet::new(), ruleset).unwrap_err();
+            assert_eq!(
+                err,
+                SetupError::Teams {
+                    teams,
+                    players: count
+                }
+            );
+        }
+        assert!(Ruleset::default().set("teams", 1).is_err());
+    }
+
+    #[test]
+    fn test_team_game_over_when_one_team_left() {
+        let mut game = multi_player_game(4, 2);
+        assert!(game.is_teammate(0, 2));
+        assert_eq!(game.opponents_of(0), vec![1, 3]);
+
+        kill(&mut game, 1);
+        assert!(game.game_over.is_none());
+        kill(&mut game, 3);
+        assert!(game.game_over.is_some());
+        assert_eq!(game.alive_players(), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_attack_target_player() {
+        let mut game = multi_player_game(3, 0);
+        let card = game.game_states[0].hand[0];
+        let zone_id = game.first_zone_id();
+        game.game_states[0].hand.retain(|&x| x != card);
+        if let Zone::FrontEnd { cards, .. } = &mut game.game_states[0].zone[0] {
+            cards.push(card);
+        }
+        // 对手1前场有卡 对手2前场为空
+        let blocker = game.game_states[1].hand[0];
+        if let Zone::FrontEnd { cards, .. } = &mut game.game_states[1].zone[0] {
+            cards.push(blocker);
+        }
+        let attack = |target| PlayerAction::AttackCard {
+            source: Targeting::TargetZone(zone_id),
+            target,
+        };
+        assert!(!game.check_attack_action(attack(Targeting::TargetPlayer(1))));
+        assert!(game.check_attack_action(attack(Targeting::TargetPlayer(2))));
+        // 不能攻击自己
+        assert!(!game.check_attack_action(attack(Targeting::TargetPlayer(0))));
+
+        game.game_states[0].real_point = 2;
+        game.game_states[2].hp = 6;
+        game.ai_players.insert(0);
+        game.deal_player_action(attack(Targeting::TargetPlayer(2)));
+        assert_eq!(game.game_states[2].hp, 4);
+        assert_eq!(game.game_states[1].hp, 6);
+    }
+
+    // === 重新读取卡片 ===
+
+    fn changed_defs(game: &Game) -> HashMap<CardInfoId, CardInfo> {
+        let mut defs = (*game.card_defs).clone();
+        defs.get_mut("test-card").unwrap().ack = 7;
+        defs
+    }
+
+    #[test]
+    fn test_reload_skips_cards_in_play() {
+        let mut game = test_game(10);
+        let card = game.game_states[0].hand[0];
+        if let Zone::FrontEnd { cards, .. } = &mut game.game_states[0].zone[0] {
+            cards.push(card);
+        }
+        let defs = changed_defs(&game);
+        let reload = game.apply_card_defs(defs);
+        assert_eq!(reload.changed, vec!["test-card".to_string()]);
+        assert_eq!(reload.skipped, 1);
+        assert_eq!(reload.updated, game.cards.len() - 1);
+        assert_eq!(game.get(card).card_info.ack, 0);
+        assert_eq!(game.get(game.game_states[1].hand[0]).card_info.ack, 7);
+    }
+
+    #[test]
+    fn test_reload_in_sandbox_updates_all() {
+        let mut game = test_game(10);
+        game.set_sandbox(true);
+        let card = game.game_states[0].hand[0];
+        if let Zone::FrontEnd { cards, .. } = &mut game.game_states[0].zone[0] {
+            cards.push(card);
+        }
+        let defs = changed_defs(&game);
+        let reload = game.apply_card_defs(defs);
+        assert_eq!(reload.skipped, 0);
+        assert_eq!(game.get(card).card_info.ack, 7);
+    }
+
+    #[test]
+    fn test_shared_card_defs_copied_on_reload() {
+        let card_defs: CardDefs = Arc::new(test_lua_api().cards);
+        let new_game = || {
+            let players = test_players(2, 10);
+            Game::with_card_defs(
+                players,
+                card_defs.clone(),
+                HashSet::new(),
+                Ruleset::default(),
+                1,
+            )
+            .unwrap()
+        };
+        let mut game = new_game();
+        let other = new_game();
+        assert!(Arc::ptr_eq(&game.card_defs, &other.card_defs));
+        let defs = changed_defs(&game);
+        game.apply_card_defs(defs);
+        assert!(!Arc::ptr_eq(&game.card_defs, &other.card_defs));
+        assert_eq!(game.card_defs["test-card"].ack, 7);
+        assert_eq!(other.card_defs["test-card"].ack, 0);
+    }
+
+    #[test]
+    fn test_save_and_resume_mid_turn() {
+        let api = test_lua_api();
+        let mut game = Game::new_with_seed(
+            test_players(2, 20),
+            &api,
+            HashSet::new(),
+            Ruleset::default(),
+            3,
+        )
+        .unwrap();
+        game.next_phase();
+        game.game_states[0].real_point = 2;
+        game.do_effect_stacks
+            .push_back(DoEffect::action(Action::Draw(1)));
+
+        let mut loaded = Game::from_save_string(&game.save_string().unwrap()).unwrap();
+        assert_eq!(loaded.current_phase, game.current_phase);
+        assert_eq!(loaded.phase_queue, game.phase_queue);
+        assert_eq!(loaded.current_player, game.current_player);
+        assert_eq!(loaded.game_states[0].real_point, 2);
+        assert_eq!(loaded.do_effect_stacks, game.do_effect_stacks);
+        assert_eq!(loaded.id_generator.next(), game.id_generator.next());
+        // 随机数从保存时的状态继续
+        for player_id in [0, 1] {
+            let hand = game.hand(player_id);
+            game.redraw(player_id, hand.clone());
+            loaded.redraw(player_id, hand);
+            assert_eq!(
+                loaded.game_states[player_id].desk,
+                game.game_states[player_id].desk
+            );
+            assert_eq!(loaded.hand(player_id), game.hand(player_id));
+        }
+    }
+
+    #[test]
+    fn test_player_view_hides_private_information() {
+        let mut game = test_game(10);
+        let card = game.game_states[0].hand[0];
+        game.game_states[0].hand.retain(|&id| id != card);
+        if let Zone::BackEnd { cards, .. } = &mut game.game_states[0].zone[4] {
+            cards.push(card);
+        }
+        game.get_mut(card).face_down = true;
+        let in_hand = game.game_states[0].hand[0];
+        let in_desk = game.game_states[0].desk[0];
+
+        let own = game.player_view(0);
+        assert_eq!(
+            own.seat(0).hand.as_ref().unwrap().len(),
+            own.seat(0).hand_count
+        );
+        assert_eq!(own.seat(0).desk, None);
+        assert_eq!(own.seat(1).hand, None);
+        assert_eq!(own.seat(1).hand_count, game.hand(1).len());
+        assert_eq!(own.seat(1).desk_count, game.game_states[1].desk.len());
+        assert!(matches!(
+            own.seat(0).zones[4].cards[0],
+            CardView::Visible { .. }
+        ));
+        assert!(own.find_card(in_hand).is_some());
+        assert!(own.find_card(in_desk).is_none());
+
+        let other = game.player_view(1);
+        assert_eq!(other.seat(0).hand, None);
+        assert_eq!(
+            other.seat(0).zones[4].cards,
+            vec![CardView::Hidden { entry_id: card }]
+        );
+        assert!(!other.seat(0).zones[4].front);
+        assert!(other.find_card(card).is_none());
+        assert!(other.find_card(in_hand).is_none());
+    }
 }
@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 /// 状态发生的变化 用于网络通讯和回放
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum GameDiff {
     #[default]
     None,
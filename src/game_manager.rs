@@ -0,0 +1,261 @@
+use crate::card::CardDefs;
+use crate::common::PlayerId;
+use crate::game::{Game, GamePhase};
+use crate::lua_api::LuaApi;
+use crate::player::Player;
+use crate::player_view::{Observer, PlayerView};
+use crate::ruleset::Ruleset;
+use crate::setup_error::SetupError;
+use crate::spectator::SpectatorMode;
+use rand::{Rng, thread_rng};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+pub type GameId = usize;
+
+/// 对局的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    // 已创建 还没有开始 可以设置控制者
+    Ready,
+    Running,
+    Finished,
+}
+
+/// 列出对局时的概要
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSummary {
+    pub id: GameId,
+    pub status: GameStatus,
+    pub player_count: usize,
+    pub turn: usize,
+    pub phase: GamePhase,
+    pub current_player: PlayerId,
+}
+
+enum Slot {
+    Ready(Box<Game>),
+    // 在单独的线程中进行 通过公开信息的观战了解进度
+    Running {
+        handle: JoinHandle<Game>,
+        stop: Arc<AtomicBool>,
+        views: Receiver<PlayerView>,
+        last: PlayerView,
+    },
+}
+
+/// 在一个进程中同时进行多局游戏 所有对局共用一份不可变的卡片数据
+///
+/// 对局的控制者应为AI或远程玩家 多局游戏不能同时读取命令行
+pub struct GameManager {
+    card_db: CardDefs,
+    next_id: GameId,
+    games: BTreeMap<GameId, Slot>,
+}
+
+impl GameManager {
+    pub fn new(card_db: LuaApi) -> Self {
+        GameManager {
+            card_db: Arc::new(card_db.cards),
+            next_id: 0,
+            games: BTreeMap::new(),
+        }
+    }
+
+    /// 创建对局 开始前可以通过 `game_mut` 设置控制者和观战
+    pub fn create(
+        &mut self,
+        players: Vec<Player>,
+        ai_players: HashSet<PlayerId>,
+        ruleset: Ruleset,
+        seed: Option<u64>,
+    ) -> Result<GameId, SetupError> {
+        let seed = seed.unwrap_or_else(|| thread_rng().r#gen());
+        let game = Game::with_card_defs(players, self.card_db.clone(), ai_players, ruleset, seed)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.games.insert(id, Slot::Ready(Box::new(game)));
+        Ok(id)
+    }
+
+    /// 还没有开始的对局
+    pub fn game_mut(&mut self, id: GameId) -> Option<&mut Game> {
+        match self.games.get_mut(&id) {
+            Some(Slot::Ready(game)) => Some(game),
+            _ => None,
+        }
+    }
+
+    /// 在新线程中开始对局 对局不存在或已经开始时返回 false
+    pub fn start(&mut self, id: GameId) -> bool {
+        if !matches!(self.games.get(&id), Some(Slot::Ready(_))) {
+            return false;
+        }
+        let Some(Slot::Ready(mut game)) = self.games.remove(&id) else {
+            return false;
+        };
+        let views = game.spectators().attach(SpectatorMode::Public);
+        let stop = game.stop_handle();
+        let last = game.view_as(Observer::Public);
+        let handle = thread::spawn(move || {
+            game.run();
+            *game
+        });
+        self.games.insert(
+            id,
+            Slot::Running {
+                handle,
+                stop,
+                views,
+                last,
+            },
+        );
+        true
+    }
+
+    pub fn status(&self, id: GameId) -> Option<GameStatus> {
+        self.games.get(&id).map(|slot| match slot {
+            Slot::Ready(_) => GameStatus::Ready,
+            Slot::Running { handle, .. } if handle.is_finished() => GameStatus::Finished,
+            Slot::Running { .. } => GameStatus::Running,
+        })
+    }
+
+    /// 按id顺序列出全部对局
+    pub fn list(&mut self) -> Vec<GameSummary> {
+        let ids: Vec<GameId> = self.games.keys().copied().collect();
+        ids.into_iter()
+            .filter_map(|id| {
+                let status = self.status(id)?;
+                let view = match self.games.get_mut(&id)? {
+                    Slot::Ready(game) => game.view_as(Observer::Public),
+                    Slot::Running { views, last, .. } => {
+                        if let Some(view) = views.try_iter().last() {
+                            *last = view;
+                        }
+                        last.clone()
+                    }
+                };
+                Some(GameSummary {
+                    id,
+                    status,
+                    player_count: view.seats.len(),
+                    turn: view.turn,
+                    phase: view.phase,
+                    current_player: view.current_player,
+                })
+            })
+            .collect()
+    }
+
+    /// 取出已经结束的对局 对局还在进行时返回 None
+    pub fn take_finished(&mut self, id: GameId) -> Option<Game> {
+        if self.status(id)? != GameStatus::Finished {
+            return None;
+        }
+        match self.games.remove(&id)? {
+            Slot::Running { handle, .. } => handle.join().ok(),
+            Slot::Ready(_) => None,
+        }
+    }
+
+    /// 删除对局 进行中的对局在下一个阶段开始前停止
+    pub fn remove(&mut self, id: GameId) -> bool {
+        match self.games.remove(&id) {
+            Some(Slot::Running { stop, .. }) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            Some(Slot::Ready(_)) => true,
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_card_api, test_players};
+    use std::time::{Duration, Instant};
+
+    fn test_manager() -> GameManager {
+        GameManager::new(test_card_api(1, 100))
+    }
+
+    // 两个AI的对局
+    fn create(manager: &mut GameManager, seed: u64) -> GameId {
+        let players = test_players(2, 20);
+        let ai_players = [0, 1].into_iter().collect();
+        manager
+            .create(players, ai_players, Ruleset::default(), Some(seed))
+            .unwrap()
+    }
+
+    fn wait_all(manager: &mut GameManager) {
+        let start = Instant::now();
+        while manager
+            .list()
+            .iter()
+            .any(|summary| summary.status != GameStatus::Finished)
+        {
+            assert!(start.elapsed() < Duration::from_secs(30));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_concurrent_games() {
+        let mut manager = test_manager();
+        let ids = [
+            create(&mut manager, 7),
+            create(&mut manager, 7),
+            create(&mut manager, 8),
+        ];
+        assert!(
+            manager
+                .list()
+                .iter()
+                .all(|summary| summary.status == GameStatus::Ready && summary.turn == 1)
+        );
+        for id in ids {
+            assert!(manager.start(id));
+        }
+        assert!(!manager.start(ids[0]));
+        wait_all(&mut manager);
+        assert!(manager.list().iter().all(|summary| summary.turn > 1));
+        let games: Vec<Game> = ids
+            .iter()
+            .map(|&id| manager.take_finished(id).unwrap())
+            .collect();
+        assert!(games.iter().all(Game::is_game_over));
+        // 相同种子的对局互不影响 结果相同
+        assert_eq!(games[0].turn_count(), games[1].turn_count());
+        assert_eq!(games[0].alive_players(), games[1].alive_players());
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn test_stop_and_remove() {
+        let mut manager = test_manager();
+        let removed = create(&mut manager, 1);
+        let stopped = create(&mut manager, 2);
+        assert!(manager.remove(removed));
+        assert!(!manager.remove(removed));
+        assert!(manager.game_mut(removed).is_none());
+
+        let stop = manager.game_mut(stopped).unwrap().stop_handle();
+        stop.store(true, Ordering::Relaxed);
+        manager.start(stopped);
+        wait_all(&mut manager);
+        let game = manager.take_finished(stopped).unwrap();
+        assert!(!game.is_game_over());
+        assert_eq!(game.turn_count(), 1);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::card::CardInfo;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// 启动时选择的语言 没有设置时使用原文
+static TRANSLATIONS: OnceLock<Translations> = OnceLock::new();
+
+/// 翻译表 `[messages]` 以中文原文为键 `[cards]` 以 `卡片id.name` 和 `卡片id.text` 为键
+///
+/// ```text
+/// [messages]
+/// 玩家[{}] 调度 {} 张卡 => Player[{}] mulligans {} cards
+/// [cards]
+/// S000-A-001.name => Test Card 001
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Translations {
+    messages: HashMap<String, String>,
+    cards: HashMap<String, String>,
+}
+
+/// 以 Debug 格式显示的参数
+pub struct Dbg<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Display for Dbg<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// 翻译消息并填入参数 参数需要实现 Display，Debug 参数用 `Dbg` 包装
+#[macro_export]
+macro_rules! tr {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::translate($fmt, &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
+
+// 翻译文件中的 `\n` 表示换行
+fn unescape(text: &str) -> String {
+    text.trim().replace("\\n", "\n")
+}
+
+impl Translations {
+    /// 解析翻译文件
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut translations = Translations::default();
+        let mut cards = false;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line {
+                "[messages]" => cards = false,
+                "[cards]" => cards = true,
+                _ => {
+                    let Some((key, value)) = line.split_once(" => ") else {
+                        return Err(format!("第{}行格式错误: {}", index + 1, line));
+                    };
+                    let table = if cards {
+                        &mut translations.cards
+                    } else {
+                        &mut translations.messages
+                    };
+                    table.insert(unescape(key), unescape(value));
+                }
+            }
+        }
+        Ok(translations)
+    }
+
+    // 读取 lang 目录下的翻译文件
+    pub fn load(dir: &Path, lang: &str) -> Result<Self, String> {
+        let path = dir.join(format!("{}.lang", lang));
+        let text = fs::read_to_string(&path).map_err(|e| format!("{:?}: {}", path, e))?;
+        Translations::parse(&text)
+    }
+
+    /// 翻译消息 没有翻译时使用原文
+    pub fn translate(&self, source: &str, args: &[&dyn fmt::Display]) -> String {
+        let template = self.messages.get(source).map_or(source, |x| x.as_str());
+        fill(template, args)
+    }
+
+    /// 使用翻译后的卡片名称和规则文本
+    pub fn localize_card(&self, card_info: &mut CardInfo) {
+        if let Some(name) = self.cards.get(&format!("{}.name", card_info.id())) {
+            card_info.name = name.clone();
+        }
+        if let Some(text) = self.cards.get(&format!("{}.text", card_info.id())) {
+            card_info.text = text.clone();
+        }
+    }
+}
+
+// 依次填入 `{}` 或按序号填入 `{0}` 其他格式原样保留
+fn fill(template: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut out = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let spec = &rest[start + 1..start + end];
+        let index = if spec.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            spec.parse::<usize>().ok()
+        };
+        match index.and_then(|index| args.get(index)) {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str(&rest[start..start + end + 1]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 设置全局使用的翻译 只能设置一次
+pub fn install(translations: Translations) {
+    let _ = TRANSLATIONS.set(translations);
+}
+
+/// 使用全局翻译表翻译消息
+pub fn translate(source: &str, args: &[&dyn fmt::Display]) -> String {
+    match TRANSLATIONS.get() {
+        Some(translations) => translations.translate(source, args),
+        None => fill(source, args),
+    }
+}
+
+/// 使用全局翻译表翻译卡片名称和规则文本
+pub fn localize_cards<'a>(cards: impl IntoIterator<Item = &'a mut CardInfo>) {
+    if let Some(translations) = TRANSLATIONS.get() {
+        for card_info in cards {
+            translations.localize_card(card_info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardInfoBuilder;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(
+            fill("玩家[{}] 调度 {} 张卡", &[&1, &3]),
+            "玩家[1] 调度 3 张卡"
+        );
+        assert_eq!(fill("{1} then {0}", &[&"a", &"b"]), "b then a");
+        assert_eq!(fill("{} {x}", &[&Dbg(vec![1])]), "[1] {x}");
+    }
+
+    #[test]
+    fn test_translate_messages_and_cards() {
+        let text = "[messages]\n玩家[{}] 调度 {} 张卡 => Player[{}] mulligans {} cards\n帮助\\n退出 => help\\nquit\n[cards]\nA.name => Card A\nA.text => Draw\\nDiscard\n";
+        let translations = Translations::parse(text).unwrap();
+        assert_eq!(
+            translations.translate("玩家[{}] 调度 {} 张卡", &[&0, &2]),
+            "Player[0] mulligans 2 cards"
+        );
+        assert_eq!(translations.translate("帮助\n退出", &[]), "help\nquit");
+        assert_eq!(translations.translate("未翻译 {}", &[&1]), "未翻译 1");
+
+        let mut card_info = CardInfoBuilder::new("A".to_string()).build();
+        translations.localize_card(&mut card_info);
+        assert_eq!(card_info.name, "Card A");
+        assert_eq!(card_info.text, "Draw\nDiscard");
+        assert!(Translations::parse("no separator").is_err());
+    }
+
+    #[test]
+    fn test_load_lang_file() {
+        let translations = Translations::load(Path::new("lang"), "en").unwrap();
+        assert_eq!(
+            translations.translate("战斗平手 {} = {}", &[&1, &1]),
+            "Battle tied 1 = 1"
+        );
+        assert!(Translations::load(Path::new("lang"), "xx").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::choice_req::ChoiceReq;
+use crate::common::{EntryId, PlayerId};
+use crate::game::{Game, GamePhase, Zone};
+use crate::player_actions::PlayerAction;
+use crate::targeting::Targeting;
+use serde::{Deserialize, Serialize};
+
+/// 选择请求的可选范围 供外部控制者决定回答
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChoiceOptions {
+    // 手卡张数加RealPoint正好等于 cost，手卡最多 cost_slots 张；也可以取消登场
+    Cost {
+        card_id: EntryId,
+        cost: usize,
+        hands: Vec<EntryId>,
+        real_point: usize,
+        cost_slots: usize,
+    },
+    // 消耗1到 real_point 点造成伤害 或放弃伤害
+    FightDamage {
+        real_point: usize,
+    },
+    // 从 cards 中正好选择 count 张
+    Reuse {
+        cards: Vec<EntryId>,
+        count: usize,
+    },
+    // 任意张手卡放回卡组
+    Mulligan {
+        hand: Vec<EntryId>,
+    },
+    // 从 count 项效果中选择一项
+    OrAction {
+        count: usize,
+    },
+}
+
+impl Game {
+    /// 当前玩家在主要阶段或战斗阶段可以执行的操作 第一个总是 Pass
+    pub fn legal_actions(&self) -> Vec<PlayerAction> {
+        let player_id = self.current_player();
+        let mut actions = vec![PlayerAction::Pass];
+        match self.current_phase() {
+            GamePhase::Main | GamePhase::Main2 => {
+                let view = self.player_view(player_id);
+                let me = view.seat(player_id);
+                let empty_zones: Vec<EntryId> = me
+                    .zones
+                    .iter()
+                    .filter(|zone| zone.cards.is_empty())
+                    .map(|zone| zone.id)
+                    .collect();
+                for card_id in self.hand(player_id) {
+                    if !self.check_cost(card_id) {
+                        continue;
+                    }
+                    for &zone_id in &empty_zones {
+                        actions.push(PlayerAction::SetCard { card_id, zone_id });
+                    }
+                }
+            }
+            GamePhase::Fight => {
+                let targets = self.attack_targets(player_id);
+                for zone in self.get_attack_zones() {
+                    let Zone::FrontEnd { id, .. } = zone else {
+                        continue;
+                    };
+                    for target in &targets {
+                        actions.push(PlayerAction::AttackCard {
+                            source: Targeting::TargetZone(id),
+                            target: target.clone(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+        actions
+    }
+
+    // 可以攻击的目标 对手前场有卡时只能攻击卡 否则直接攻击玩家
+    fn attack_targets(&self, player_id: PlayerId) -> Vec<Targeting> {
+        let mut targets = Vec::new();
+        for opponent in self.opponents_of(player_id) {
+            let zones = self.attacked_zones_of(opponent);
+            if zones.is_empty() {
+                targets.push(Targeting::TargetPlayer(opponent));
+            }
+            for zone in zones {
+                if let Zone::FrontEnd { id, .. } = zone {
+                    targets.push(Targeting::TargetZone(id));
+                }
+            }
+        }
+        targets
+    }
+
+    /// 选择请求的可选范围
+    pub fn choice_options(&self, choice: &ChoiceReq) -> ChoiceOptions {
+        match choice {
+            ChoiceReq::Cost(card_id) => {
+                let player_id = self.current_player();
+                ChoiceOptions::Cost {
+                    card_id: *card_id,
+                    cost: self.get(*card_id).card_info.cost,
+                    hands: self.hand(player_id),
+                    real_point: self.real_point(player_id),
+                    cost_slots: self.available_cost_slots(player_id),
+                }
+            }
+            ChoiceReq::FightDamage => ChoiceOptions::FightDamage {
+                real_point: self.current_real_point(),
+            },
+            ChoiceReq::Reuse { player_id, limit } => {
+                let cards = self.cost_zone(*player_id);
+                ChoiceOptions::Reuse {
+                    count: (*limit).min(cards.len()),
+                    cards,
+                }
+            }
+            ChoiceReq::Mulligan(player_id) => ChoiceOptions::Mulligan {
+                hand: self.hand(*player_id),
+            },
+            ChoiceReq::OrAction(effects) => ChoiceOptions::OrAction {
+                count: effects.len(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choice_res::ChoiceRes;
+    use crate::remote::{ClientMessage, RemoteSeat, ServerMessage};
+    use crate::ruleset::Ruleset;
+    use crate::test_util::{test_card_api, test_players};
+    use std::collections::HashSet;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::thread;
+
+    // 只根据消息中的可选范围回答 操作总是选择最后一个
+    fn answer(options: &ChoiceOptions) -> ChoiceRes {
+        match options {
+            ChoiceOptions::Cost {
+                cost,
+                hands,
+                real_point,
+                cost_slots,
+                ..
+            } => {
+                let hands: Vec<EntryId> = hands
+                    .iter()
+                    .take((*cost).min(*cost_slots))
+                    .copied()
+                    .collect();
+                if hands.len() + real_point < *cost {
+                    return ChoiceRes::None;
+                }
+                let real_point = cost - hands.len();
+                ChoiceRes::Cost { hands, real_point }
+            }
+            ChoiceOptions::FightDamage { real_point: 0 } => ChoiceRes::None,
+            ChoiceOptions::FightDamage { real_point } => {
+                ChoiceRes::FightDamageByRealPoint(*real_point)
+            }
+            ChoiceOptions::Reuse { cards, count } => {
+                ChoiceRes::Reuse(cards.iter().take(*count).copied().collect())
+            }
+            ChoiceOptions::Mulligan { .. } => ChoiceRes::Mulligan(Vec::new()),
+            ChoiceOptions::OrAction { count } => ChoiceRes::OrAction(count - 1),
+        }
+    }
+
+    #[test]
+    fn test_jsonl_controller_plays_from_options() {
+        let api = test_card_api(1, 100);
+        let players = test_players(2, 20);
+        let mut game = Game::new(players, &api, HashSet::new(), Ruleset::default()).unwrap();
+
+        // 两个座位共用一对管道 和标准输入输出一样
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, mut reply_writer) = io::pipe().unwrap();
+        let seat = RemoteSeat::new(BufReader::new(reply_reader), request_writer);
+        game.set_remote_player(0, seat.clone());
+        game.set_remote_player(1, seat);
+        let bot = thread::spawn(move || {
+            let mut actions = Vec::new();
+            for line in BufReader::new(request_reader).lines() {
+                let reply = match serde_json::from_str(&line.unwrap()).unwrap() {
+                    ServerMessage::Action { options, .. } => {
+                        assert_eq!(options[0], PlayerAction::Pass);
+                        let action = options.last().unwrap().clone();
+                        actions.push(action.clone());
+                        ClientMessage::Action { action }
+                    }
+                    ServerMessage::Choice { options, .. } => ClientMessage::Choice {
+                        choice: answer(&options),
+                    },
+                    ServerMessage::Error { message } => panic!("{}", message),
+                    _ => continue,
+                };
+                serde_json::to_writer(&mut reply_writer, &reply).unwrap();
+                writeln!(reply_writer).unwrap();
+            }
+            actions
+        });
+        game.run();
+        assert!(game.is_game_over());
+        drop(game);
+
+        let actions = bot.join().unwrap();
+        assert!(
+            actions
+                .iter()
+                .any(|action| matches!(action, PlayerAction::SetCard { .. }))
+        );
+        assert!(
+            actions
+                .iter()
+                .any(|action| matches!(action, PlayerAction::AttackCard { .. }))
+        );
+    }
+}
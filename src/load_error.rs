@@ -0,0 +1,253 @@
+use crate::tr;
+use log::error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// 读取卡片、卡组和规则时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    // 目录或文件读取失败
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+    // 脚本执行失败
+    Script {
+        path: PathBuf,
+        line: Option<usize>,
+        card_id: Option<String>,
+        message: String,
+        traceback: Option<String>,
+    },
+    // JSON/TOML 卡片数据错误
+    CardData {
+        path: PathBuf,
+        card_id: Option<String>,
+        message: String,
+    },
+    // 规则文件格式错误
+    Ruleset {
+        path: PathBuf,
+        message: String,
+    },
+    // 卡组文件格式错误
+    DeckSyntax {
+        deck: String,
+        line: usize,
+        message: String,
+    },
+    // 卡组引用了不存在的卡片
+    UnknownCard {
+        deck: String,
+        line: usize,
+        card_id: String,
+    },
+}
+
+impl LoadError {
+    pub fn io(path: &Path, err: std::io::Error) -> Self {
+        LoadError::Io {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        }
+    }
+
+    // 从Lua错误中提取行号和调用栈 脚本的chunk名需为 `@文件路径`
+    pub fn script(path: &Path, card_id: Option<String>, err: &mlua::Error) -> Self {
+        let mut traceback = None;
+        let mut cause = err;
+        while let mlua::Error::CallbackError {
+            traceback: trace,
+            cause: inner,
+        } = cause
+        {
+            traceback.get_or_insert_with(|| trace.clone());
+            cause = inner;
+        }
+        let text = match cause {
+            mlua::Error::SyntaxError { message, .. } => message.clone(),
+            mlua::Error::RuntimeError(message) => message.clone(),
+            other => other.to_string(),
+        };
+        // 脚本中抛出的错误自带调用栈
+        let (message, trace) = match text.split_once("\nstack traceback:") {
+            Some((message, trace)) => (message.to_string(), Some(trace.trim().to_string())),
+            None => (text, None),
+        };
+        LoadError::Script {
+            path: path.to_path_buf(),
+            line: script_line(path, &message),
+            card_id,
+            message,
+            traceback: trace.or(traceback),
+        }
+    }
+}
+
+// 错误信息形如 `cards/a.lua:3: xxx`
+fn script_line(path: &Path, message: &str) -> Option<usize> {
+    let prefix = format!("{}:", path.display());
+    let start = message.find(&prefix)? + prefix.len();
+    let digits: String = message[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, message } => {
+                write!(f, "{} 读取失败: {}", path.display(), message)
+            }
+            LoadError::Script {
+                path,
+                line,
+                card_id,
+                message,
+                traceback,
+            } => {
+                write!(f, "{}", path.display())?;
+                if let Some(line) = line {
+                    write!(f, " 第{}行", line)?;
+                }
+                if let Some(card_id) = card_id {
+                    write!(f, " 卡片[{}]", card_id)?;
+                }
+                write!(f, " 脚本错误: {}", message)?;
+                if let Some(traceback) = traceback {
+                    write!(f, "\nstack traceback:\n{}", traceback)?;
+                }
+                Ok(())
+            }
+            LoadError::CardData {
+                path,
+                card_id,
+                message,
+            } => {
+                write!(f, "{}", path.display())?;
+                if let Some(card_id) = card_id {
+                    write!(f, " 卡片[{}]", card_id)?;
+                }
+                write!(f, " 卡片数据错误: {}", message)
+            }
+            LoadError::Ruleset { path, message } => {
+                write!(f, "{} 规则错误: {}", path.display(), message)
+            }
+            LoadError::DeckSyntax {
+                deck,
+                line,
+                message,
+            } => write!(f, "卡组[{}] 第{}行 {}", deck, line, message),
+            LoadError::UnknownCard {
+                deck,
+                line,
+                card_id,
+            } => write!(f, "卡组[{}] 第{}行 卡片[{}]不存在", deck, line, card_id),
+        }
+    }
+}
+
+/// 读取结果
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    // 出错时是否继续读取其他文件
+    pub keep_going: bool,
+    // 成功读取的文件
+    pub loaded: Vec<PathBuf>,
+    // 继续读取模式下收集的错误
+    pub errors: Vec<LoadError>,
+}
+
+impl LoadReport {
+    pub fn new(keep_going: bool) -> Self {
+        Self {
+            keep_going,
+            ..Default::default()
+        }
+    }
+
+    // 记录错误 继续读取模式下返回Ok 否则把错误返回给调用者
+    pub fn fail(&mut self, load_error: LoadError) -> Result<(), LoadError> {
+        if !self.keep_going {
+            return Err(load_error);
+        }
+        error!("{}", load_error);
+        self.errors.push(load_error);
+        Ok(())
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                "读取 {} 个文件，{} 个错误",
+                self.loaded.len(),
+                self.errors.len()
+            )
+        )?;
+        for load_error in &self.errors {
+            write!(f, "\n  {}", load_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mlua::Lua;
+
+    #[test]
+    fn test_script_error_line() {
+        let lua = Lua::new();
+        let path = Path::new("cards/broken.lua");
+        let err = lua
+            .load("local a = 1\nerror('boom')")
+            .set_name("@cards/broken.lua")
+            .exec()
+            .unwrap_err();
+        let LoadError::Script { line, message, .. } = LoadError::script(path, None, &err) else {
+            panic!("应为脚本错误");
+        };
+        assert_eq!(line, Some(2));
+        assert!(message.contains("boom"));
+    }
+
+    #[test]
+    fn test_syntax_error_line() {
+        let lua = Lua::new();
+        let path = Path::new("cards/broken.lua");
+        let err = lua
+            .load("\n\nlocal = 1")
+            .set_name("@cards/broken.lua")
+            .exec()
+            .unwrap_err();
+        let load_error = LoadError::script(path, Some("X".to_string()), &err);
+        assert!(matches!(
+            load_error,
+            LoadError::Script { line: Some(3), .. }
+        ));
+    }
+
+    #[test]
+    fn test_report_keep_going() {
+        let load_error = LoadError::Io {
+            path: PathBuf::from("cards"),
+            message: "missing".to_string(),
+        };
+        let mut strict = LoadReport::new(false);
+        assert!(strict.fail(load_error.clone()).is_err());
+        let mut report = LoadReport::new(true);
+        assert!(report.fail(load_error).is_ok());
+        assert!(!report.is_ok());
+    }
+}
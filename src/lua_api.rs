@@ -1,39 +1,153 @@
 use crate::card::{CardInfo, CardInfoBuilder};
-use mlua::prelude::{LuaError, LuaUserDataMethods};
-use mlua::{AnyUserData, Function, Lua};
+use crate::ruleset::Ruleset;
+use crate::sandbox::run_limited;
+use mlua::prelude::LuaError;
+use mlua::{Function, Lua};
 use std::collections::HashMap;
 
 /// 脚本上下文
-
+#[derive(Debug, Default)]
 pub struct LuaApi {
     pub cards: HashMap<String, CardInfo>,
+    pub rulesets: HashMap<String, Ruleset>,
+    // 正在定义的卡片 脚本出错时用来定位卡片
+    pub defining_card: Option<String>,
 }
 
 impl LuaApi {
     pub fn new() -> Self {
-        Self {
-            cards: HashMap::new(),
-        }
+        Self::default()
     }
 
-    // 初始化Lua脚本环境
-    pub fn install(&mut self, lua: &Lua) -> Result<(), LuaError> {
-        let mut api_ptr = std::ptr::NonNull::from(self);
-        let define_card = lua.create_function_mut(move |lua, (id, func): (String, Function)| {
-            let api = unsafe { api_ptr.as_mut() };
+    // 初始化Lua脚本环境 注册的函数通过 app data 访问正在读取脚本的 LuaApi
+    pub fn install(lua: &Lua) -> Result<(), LuaError> {
+        let define_card = lua.create_function(|lua, (id, func): (String, Function)| {
+            with_api(lua, |api| {
+                api.defining_card = Some(id.clone());
+                if api.cards.contains_key(&id) {
+                    return Err(LuaError::RuntimeError(format!("卡片[{}]重复定义", id)));
+                }
+                Ok(())
+            })??;
 
-            let mut card = CardInfoBuilder::new(id.clone());
+            let card = CardInfoBuilder::new(id.clone());
 
             let card_ud = lua.create_userdata(card)?;
-            func.call::<()>(card_ud.clone())?;
+            run_limited(lua, || func.call::<()>(card_ud.clone()))?;
 
             let card = card_ud.take::<CardInfoBuilder>()?;
-            api.cards.insert(id, card.build());
-
-            Ok(())
+            with_api(lua, |api| {
+                api.cards.insert(id, card.build());
+                api.defining_card = None;
+            })
         })?;
 
         lua.globals().set("define_card", define_card)?;
+
+        let define_ruleset = lua.create_function(|lua, (name, func): (String, Function)| {
+            let rules_ud = lua.create_userdata(Ruleset::new(name.clone()))?;
+            run_limited(lua, || func.call::<()>(rules_ud.clone()))?;
+
+            let ruleset = rules_ud.take::<Ruleset>()?;
+            with_api(lua, |api| {
+                api.rulesets.insert(name, ruleset);
+            })
+        })?;
+
+        lua.globals().set("define_ruleset", define_ruleset)?;
         Ok(())
     }
+
+    /// 按条件查询卡片定义 结果按卡片id排序
+    pub fn find_cards(&self, filter: impl Fn(&CardInfo) -> bool) -> Vec<&CardInfo> {
+        let mut cards: Vec<&CardInfo> = self.cards.values().filter(|card| filter(card)).collect();
+        cards.sort_by(|a, b| a.id().cmp(b.id()));
+        cards
+    }
+
+    /// 卡包中的全部卡片
+    pub fn cards_in_set(&self, set_code: &str) -> Vec<&CardInfo> {
+        self.find_cards(|card| card.meta.set_code == set_code)
+    }
+
+    /// 执行脚本期间把自己交给Lua 脚本注册的卡片和规则写入自己
+    pub fn load_with<R>(&mut self, lua: &Lua, f: impl FnOnce() -> R) -> R {
+        lua.set_app_data(std::mem::take(self));
+        let result = f();
+        if let Some(api) = lua.remove_app_data::<LuaApi>() {
+            *self = api;
+        }
+        result
+    }
+}
+
+// 访问正在读取脚本的 LuaApi 不在回调期间持有借用
+fn with_api<R>(lua: &Lua, f: impl FnOnce(&mut LuaApi) -> R) -> mlua::Result<R> {
+    let mut api = lua
+        .app_data_mut::<LuaApi>()
+        .ok_or_else(|| LuaError::RuntimeError("只能在读取脚本时定义卡片和规则".to_string()))?;
+    Ok(f(&mut api))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_meta::Rarity;
+
+    #[test]
+    fn test_load_into_multiple_lua() {
+        let code = r#"define_card("A", function(card) card:cost(1) end)"#;
+        let mut api = LuaApi::new();
+        for _ in 0..2 {
+            let lua = Lua::new();
+            LuaApi::install(&lua).unwrap();
+            let mut other = LuaApi::new();
+            other.load_with(&lua, || lua.load(code).exec()).unwrap();
+            assert!(other.cards.contains_key("A"));
+            api.cards.extend(other.cards);
+        }
+        // 没有交给Lua时不能定义卡片
+        let lua = Lua::new();
+        LuaApi::install(&lua).unwrap();
+        assert!(lua.load(code).exec().is_err());
+        // 重复读取同一个环境
+        api.load_with(&lua, || {
+            lua.load(r#"define_card("B", function(card) end)"#).exec()
+        })
+        .unwrap();
+        assert_eq!(api.cards.len(), 2);
+    }
+
+    #[test]
+    fn test_query_card_meta() {
+        let lua = Lua::new();
+        LuaApi::install(&lua).unwrap();
+        let mut api = LuaApi::new();
+        api.load_with(&lua, || {
+            lua.load(
+                r#"
+                define_card("S000-A-002", function(card) card:rarity("sr") end)
+                define_card("S000-A-001", function(card) end)
+                define_card("promo", function(card)
+                    card:set_code("P01")
+                    card:number(7)
+                    card:version("1.1")
+                end)
+                "#,
+            )
+            .exec()
+        })
+        .unwrap();
+        let ids: Vec<&str> = api
+            .cards_in_set("S000")
+            .iter()
+            .map(|card| card.id())
+            .collect();
+        assert_eq!(ids, vec!["S000-A-001", "S000-A-002"]);
+        let rare = api.find_cards(|card| card.meta.rarity >= Rarity::SuperRare);
+        assert_eq!(rare.len(), 1);
+        let promo = &api.cards["promo"].meta;
+        assert_eq!((promo.set_code.as_str(), promo.number), ("P01", 7));
+        assert_eq!(promo.version.as_deref(), Some("1.1"));
+    }
 }
@@ -1,40 +1,130 @@
 use crate::card_loader::load_cards;
+use crate::card_reload::CardSource;
 use crate::lua_api::LuaApi;
-use mlua::Lua;
 use std::collections::HashSet;
 use std::env;
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 mod ai;
 mod card;
+mod card_data;
 mod card_loader;
+mod card_meta;
+mod card_reload;
+mod card_text;
+mod choice_check;
+mod choice_error;
 mod choice_req;
 mod choice_res;
 mod command_reader;
 mod common;
+mod deck_check;
 mod desk_loader;
 mod effect;
 mod game;
 mod game_diff;
+mod game_manager;
+mod i18n;
+mod legal_actions;
+mod load_error;
 mod lua_api;
 mod player;
 mod player_actions;
+mod player_view;
+mod remote;
+mod ruleset;
+mod ruleset_loader;
+mod sandbox;
+mod save_game;
+mod setup_error;
+mod spectator;
 mod targeting;
+#[cfg(test)]
+mod test_util;
+mod time_control;
+mod validate;
 mod window_event;
 
+use crate::card_text::export_card_texts;
+use crate::deck_check::check_deck;
 use crate::desk_loader::load_desks;
-use crate::effect::EffectBuilder;
 use crate::game::Game;
-use crate::player::Player;
-use log::{debug, error, info, log_enabled, Level};
+use crate::game_manager::{GameManager, GameStatus};
+use crate::i18n::Dbg;
+use crate::i18n::Translations;
+use crate::load_error::LoadReport;
+use crate::player::{Player, PlayerDesk};
+use crate::remote::{accept_players, attach_stdio};
+use crate::ruleset::Ruleset;
+use crate::ruleset_loader::load_rulesets;
+use crate::sandbox::new_lua;
+use crate::spectator::{SpectatorMode, write_views};
+use crate::validate::check_cards;
+use log::{debug, error, info, warn};
 
-fn main() {
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    // --controller jsonl 通过标准输入输出的JSON行控制玩家 日志改为输出到标准错误
+    let jsonl_controller = args
+        .windows(2)
+        .any(|pair| pair[0] == "--controller" && pair[1] == "jsonl");
     env_logger::builder()
-        .target(env_logger::Target::Stdout)
+        .target(if jsonl_controller {
+            env_logger::Target::Stderr
+        } else {
+            env_logger::Target::Stdout
+        })
         .filter_level(log::LevelFilter::Info)
         .try_init()
         .unwrap();
 
-    let args: Vec<String> = env::args().collect();
     let mut ai_players: HashSet<usize> = HashSet::new();
+    let mut ruleset_name = None;
+    let mut seed_arg = None;
+    let mut players_arg = None;
+    // 中文是原文 其他语言从 lang 目录读取
+    let mut lang = "zh".to_string();
+    // 读取存档继续游戏
+    let mut load_path = None;
+    // 观战记录写入的文件 延迟为0时只有公开信息
+    let mut spectate_path = None;
+    let mut spectate_delay_arg = None;
+    // 导出时只导出这个卡包的卡片
+    let mut set_code = None;
+    // simulate 中超过这个回合数的对局被停止 0为不限制
+    let mut max_turns_arg = None;
+    // validate 只检查卡片和卡组数据 不开始游戏
+    let validate_only = args.get(1).is_some_and(|arg| arg == "validate");
+    // export 导出卡片文本 默认写入 card_texts.tsv
+    let export_path = (args.get(1).is_some_and(|arg| arg == "export")).then(|| {
+        args.get(2)
+            .filter(|arg| !arg.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| "card_texts.tsv".to_string())
+    });
+    // simulate 同时进行多局AI对局 默认10局
+    let simulate_mode = args.get(1).is_some_and(|arg| arg == "simulate");
+    let simulate_count_arg = args
+        .get(2)
+        .filter(|arg| simulate_mode && !arg.starts_with("--"));
+    // server 通过TCP接受不是AI的玩家 默认监听 127.0.0.1:7878
+    let server_addr = (args.get(1).is_some_and(|arg| arg == "server")).then(|| {
+        args.get(2)
+            .filter(|arg| !arg.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| "127.0.0.1:7878".to_string())
+    });
+    // 读取出错时跳过出错的文件继续读取
+    let keep_going = validate_only || args.iter().any(|arg| arg == "--keep-going");
+    // 卡片脚本变化时自动重新读取
+    let watch = args.iter().any(|arg| arg == "--watch");
+    // 沙盒模式 重新读取时场上的卡也更新
+    let sandbox = args.iter().any(|arg| arg == "--sandbox");
 
     for i in 0..args.len() {
         if args[i] == "--ai" && i + 1 < args.len() {
@@ -45,36 +135,434 @@ fn main() {
                 }
             }
         }
+        if args[i] == "--ruleset" && i + 1 < args.len() {
+            ruleset_name = Some(args[i + 1].clone());
+        }
+        if args[i] == "--seed" && i + 1 < args.len() {
+            seed_arg = Some(args[i + 1].clone());
+        }
+        if args[i] == "--players" && i + 1 < args.len() {
+            players_arg = Some(args[i + 1].clone());
+        }
+        if args[i] == "--load" && i + 1 < args.len() {
+            load_path = Some(args[i + 1].clone());
+        }
+        if args[i] == "--spectate" && i + 1 < args.len() {
+            spectate_path = Some(args[i + 1].clone());
+        }
+        if args[i] == "--spectate-delay" && i + 1 < args.len() {
+            spectate_delay_arg = Some(args[i + 1].clone());
+        }
+        if args[i] == "--max-turns" && i + 1 < args.len() {
+            max_turns_arg = Some(args[i + 1].clone());
+        }
+        if args[i] == "--set" && i + 1 < args.len() {
+            set_code = Some(args[i + 1].clone());
+        }
+        if args[i] == "--lang" && i + 1 < args.len() {
+            lang = args[i + 1].clone();
+        }
     }
 
+    if lang != "zh" {
+        match Translations::load(Path::new("lang"), &lang) {
+            Ok(translations) => i18n::install(translations),
+            Err(e) => {
+                error!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let player_count = match parse_number("--players", players_arg.as_ref()) {
+        Ok(player_count) => player_count.unwrap_or(2),
+        Err(code) => return code,
+    };
+    if !(2..=4).contains(&player_count) {
+        error!("{}", tr!("玩家人数只能是2到4人"));
+        return ExitCode::FAILURE;
+    }
+    let seed: Option<u64> = match parse_number("--seed", seed_arg.as_ref()) {
+        Ok(seed) => seed,
+        Err(code) => return code,
+    };
+    let spectate_delay = match parse_number("--spectate-delay", spectate_delay_arg.as_ref()) {
+        Ok(spectate_delay) => spectate_delay.unwrap_or(0),
+        Err(code) => return code,
+    };
+    let max_turns = match parse_number("--max-turns", max_turns_arg.as_ref()) {
+        Ok(max_turns) => max_turns.unwrap_or(0),
+        Err(code) => return code,
+    };
+    let simulate_count = match parse_number("simulate", simulate_count_arg) {
+        Ok(count) => simulate_mode.then(|| count.unwrap_or(10)),
+        Err(code) => return code,
+    };
+
     if !ai_players.is_empty() {
         info!("AI players: {:?}", ai_players);
     }
 
     info!("Start!");
     let mut api = LuaApi::new();
-    let mut lua = Lua::new();
+    // 卡片脚本在沙盒中运行 重新读取时使用同样的目录和限制
+    let card_source = CardSource::default();
+    let lua = match new_lua(card_source.limits) {
+        Ok(lua) => lua,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut report = LoadReport::new(keep_going);
     info!("Loading cards...");
-    load_cards(&lua, &mut api);
+    if let Err(e) = load_cards(&lua, &mut api, &card_source.dir, &mut report) {
+        error!("{}", e);
+        return ExitCode::FAILURE;
+    }
     info!("cards loaded!");
     info!("All {:?}", api.cards);
 
+    if let Some(path) = export_path {
+        let cards = match &set_code {
+            Some(set_code) => api.cards_in_set(set_code),
+            None => api.find_cards(|_| true),
+        };
+        return match fs::write(&path, export_card_texts(&cards)) {
+            Ok(()) => {
+                info!("{}", tr!("导出 {} 张卡片的文本到 {}", cards.len(), path));
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                error!("{}: {}", path, e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    info!("Loading rulesets...");
+    if let Err(e) = load_rulesets(&lua, &mut api, &mut report) {
+        error!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    let ruleset = match ruleset_name {
+        Some(name) => match api.rulesets.get(&name) {
+            Some(ruleset) => ruleset.clone(),
+            None => {
+                error!("Ruleset {} not found", name);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => api.rulesets.get("standard").cloned().unwrap_or_default(),
+    };
+    info!("ruleset: {:?}", ruleset);
+
     info!("Loading desk");
-    let desks = load_desks();
+    let desks = match load_desks(&api, &mut report) {
+        Ok(desks) => desks,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
     info!("desk loaded!");
+    if report.is_ok() {
+        info!("{}", report);
+    } else {
+        warn!("{}", report);
+    }
 
-    if let Some(desk_test) = desks.get("test1") {
-        let player_0 = Player {
-            id: 0,
-            player_desk: desk_test.clone(),
+    if validate_only {
+        let issues = check_cards(&api, &ruleset);
+        for issue in &issues {
+            error!("{}", issue);
+        }
+        // 卡组按选择的规则检查
+        let mut deck_names: Vec<&String> = desks.keys().collect();
+        deck_names.sort();
+        let mut violation_count = 0;
+        for name in deck_names {
+            for violation in check_deck(&desks[name].main, &ruleset) {
+                error!("{}", tr!("卡组[{}] {}", name, violation));
+                violation_count += 1;
+            }
+        }
+        info!(
+            "{}",
+            tr!(
+                "检查 {} 张卡片，{} 个卡组，{} 个读取错误，{} 个卡片问题，{} 个卡组问题",
+                api.cards.len(),
+                desks.len(),
+                report.errors.len(),
+                issues.len(),
+                violation_count
+            )
+        );
+        return if report.is_ok() && issues.is_empty() && violation_count == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
         };
-        let player_1 = Player {
-            id: 1,
-            player_desk: desk_test.clone(),
+    }
+
+    if let Some(count) = simulate_count {
+        let Some(deck) = desks.get("test1") else {
+            error!("{}", tr!("卡组[{}]不存在", "test1"));
+            return ExitCode::FAILURE;
         };
+        let simulation = Simulation {
+            count,
+            seed,
+            max_turns,
+            spectate_path: spectate_path.as_deref(),
+            spectate_delay,
+        };
+        return simulate(api, &deck.main, player_count, ruleset, simulation);
+    }
 
-        let mut game = Game::new(vec![player_0, player_1], &api, ai_players);
+    // 从存档继续 卡组和规则使用存档中的
+    if let Some(path) = load_path {
+        info!("{}", tr!("读取存档 {}", path));
+        let mut game = match Game::load_from(Path::new(&path)) {
+            Ok(game) => game,
+            Err(e) => {
+                error!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        // 指定了 --ai 时替换存档中的AI玩家
+        if !ai_players.is_empty() {
+            game.set_ai_players(ai_players);
+        }
+        game.set_sandbox(sandbox);
+        game.set_card_source(card_source.clone());
+        if watch {
+            game.watch_cards();
+        }
+        if jsonl_controller && let Err(e) = attach_stdio(&mut game) {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+        if let Some(addr) = &server_addr
+            && let Err(e) = host(&mut game, addr)
+        {
+            error!("{}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+        let spectator = match spectate(&game, spectate_path.as_deref(), spectate_delay) {
+            Ok(spectator) => spectator,
+            Err(e) => {
+                error!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        game.resume();
+        finish_spectating(game, spectator);
+        return ExitCode::SUCCESS;
+    }
+
+    if let Some(deck) = desks.get("test1") {
+        info!(
+            "{}",
+            tr!("卡组: {} 主卡组{}张", deck.name, deck.main.0.len())
+        );
+        let desk_test = &deck.main;
+        let players: Vec<Player> = (0..player_count)
+            .map(|id| Player {
+                id,
+                player_desk: desk_test.clone(),
+            })
+            .collect();
+        let game = match seed {
+            Some(seed) => Game::new_with_seed(players, &api, ai_players, ruleset, seed),
+            None => Game::new(players, &api, ai_players, ruleset),
+        };
+        let mut game = match game {
+            Ok(game) => game,
+            Err(e) => {
+                error!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        game.set_sandbox(sandbox);
+        game.set_card_source(card_source.clone());
+        if watch {
+            game.watch_cards();
+        }
+        if jsonl_controller && let Err(e) = attach_stdio(&mut game) {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+        if let Some(addr) = &server_addr
+            && let Err(e) = host(&mut game, addr)
+        {
+            error!("{}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+        let spectator = match spectate(&game, spectate_path.as_deref(), spectate_delay) {
+            Ok(spectator) => spectator,
+            Err(e) => {
+                error!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
         debug!("Game {:?}", game);
         game.run();
+        finish_spectating(game, spectator);
+    }
+    ExitCode::SUCCESS
+}
+
+// 指定了 --spectate 时加入一个观战者 把看到的状态写入文件
+fn spectate(
+    game: &Game,
+    path: Option<&str>,
+    delay: usize,
+) -> std::io::Result<Option<JoinHandle<()>>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let mode = if delay == 0 {
+        SpectatorMode::Public
+    } else {
+        SpectatorMode::Delayed(delay)
+    };
+    info!("{}", tr!("观战记录写入 {}", path));
+    let receiver = game.spectators().attach(mode);
+    write_views(receiver, Path::new(path)).map(Some)
+}
+
+// 对局结束后关闭观战 等待记录全部写入
+fn finish_spectating(game: Game, spectator: Option<JoinHandle<()>>) {
+    drop(game);
+    if let Some(handle) = spectator {
+        let _ = handle.join();
+    }
+}
+
+// 监听地址 等待所有远程玩家连接
+fn host(game: &mut Game, addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    info!("{}", tr!("服务器监听 {}", listener.local_addr()?));
+    accept_players(game, &listener)
+}
+
+// simulate 的设置
+struct Simulation<'a> {
+    count: usize,
+    seed: Option<u64>,
+    // 超过这个回合数的对局被停止 0为不限制
+    max_turns: usize,
+    // 第一局的观战记录
+    spectate_path: Option<&'a str>,
+    spectate_delay: usize,
+}
+
+// 同时进行多局全部由AI控制的对局 输出每局的结果和各座位的胜场
+fn simulate(
+    api: LuaApi,
+    desk: &PlayerDesk,
+    player_count: usize,
+    ruleset: Ruleset,
+    simulation: Simulation,
+) -> ExitCode {
+    let mut manager = GameManager::new(api);
+    let ai_players: HashSet<usize> = (0..player_count).collect();
+    let mut spectator = None;
+    for index in 0..simulation.count {
+        let players = (0..player_count)
+            .map(|id| Player {
+                id,
+                player_desk: desk.clone(),
+            })
+            .collect();
+        let game_seed = simulation.seed.map(|seed| seed + index as u64);
+        match manager.create(players, ai_players.clone(), ruleset.clone(), game_seed) {
+            Ok(id) => {
+                if index == 0 {
+                    let game = manager.game_mut(id).unwrap();
+                    match spectate(game, simulation.spectate_path, simulation.spectate_delay) {
+                        Ok(handle) => spectator = handle,
+                        Err(e) => {
+                            error!("{}", e);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                manager.start(id);
+            }
+            Err(e) => {
+                error!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let mut wins = vec![0; player_count];
+    let mut stopped = 0;
+    while !manager.is_empty() {
+        for summary in manager.list() {
+            if summary.status == GameStatus::Running
+                && simulation.max_turns > 0
+                && summary.turn > simulation.max_turns
+            {
+                manager.remove(summary.id);
+                stopped += 1;
+                info!(
+                    "{}",
+                    tr!(
+                        "对局[{}] 超过{}回合，停止对局",
+                        summary.id,
+                        simulation.max_turns
+                    )
+                );
+                continue;
+            }
+            if summary.status != GameStatus::Finished {
+                continue;
+            }
+            let Some(game) = manager.take_finished(summary.id) else {
+                continue;
+            };
+            let winners = game.alive_players();
+            for &winner in &winners {
+                wins[winner] += 1;
+            }
+            info!(
+                "{}",
+                tr!(
+                    "对局[{}] 种子[{}] 第{}回合结束 胜者{}",
+                    summary.id,
+                    game.seed(),
+                    game.turn_count(),
+                    Dbg(&winners)
+                )
+            );
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    for (player_id, win) in wins.iter().enumerate() {
+        info!("{}", tr!("玩家[{}] 胜{}局", player_id, win));
+    }
+    if stopped > 0 {
+        info!("{}", tr!("{}局超过回合限制被停止", stopped));
+    }
+    // 被停止的对局在线程结束后关闭观战
+    if let Some(handle) = spectator {
+        let _ = handle.join();
+    }
+    ExitCode::SUCCESS
+}
+
+// 解析数字参数 不是非负整数时报错退出
+fn parse_number<T: FromStr>(flag: &str, arg: Option<&String>) -> Result<Option<T>, ExitCode> {
+    match arg {
+        Some(arg) => match arg.parse() {
+            Ok(num) => Ok(Some(num)),
+            Err(_) => {
+                error!("{}", tr!("{} 必须是非负整数: {}", flag, arg));
+                Err(ExitCode::FAILURE)
+            }
+        },
+        None => Ok(None),
     }
 }
@@ -1,12 +1,26 @@
 use crate::common::{CardInfoId, PlayerId};
+use serde::{Deserialize, Serialize};
 
 /// 玩家信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub id: PlayerId,
     pub player_desk: PlayerDesk,
 }
 
 /// 玩家卡组信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PlayerDesk(pub Vec<CardInfoId>);
+
+/// 卡组文件 包括主卡组和备牌
+#[derive(Debug, Clone, Default)]
+pub struct Deck {
+    // 卡组名称 未设置时为文件名
+    pub name: String,
+    // 作者
+    pub author: Option<String>,
+    // 主卡组
+    pub main: PlayerDesk,
+    // 备牌
+    pub side: PlayerDesk,
+}
@@ -1,9 +1,10 @@
-use crate::card::Card;
+use crate::choice_error::ChoiceError;
 use crate::choice_req::ChoiceReq;
 use crate::choice_res::ChoiceRes;
-use crate::common::EntryId;
-use crate::game::{Game, GamePhase};
+use crate::common::{EntryId, PlayerId};
 use crate::targeting::Targeting;
+use crate::time_control::Deadline;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 pub trait ReadPlayerActions {
@@ -12,16 +13,30 @@ pub trait ReadPlayerActions {
 
     // 在战斗阶段读取数据
     fn read_action_fight(&mut self);
-    fn read_fight_damage(&mut self) -> ChoiceRes;
+    fn read_fight_damage(&mut self, deadline: Option<Deadline>) -> ChoiceRes;
 
-    // 阅读选择指令
-    fn read_choice(&mut self, choice: ChoiceReq) -> ChoiceRes;
-    fn read_reuse_choice(&mut self, targeting: Targeting, limit: usize) -> Vec<EntryId>;
+    // 阅读选择指令 deadline 为这次选择的时限 重新询问时不变
+    fn read_choice(
+        &mut self,
+        player_id: PlayerId,
+        choice: ChoiceReq,
+        deadline: Option<Deadline>,
+    ) -> ChoiceRes;
+    // 回答不合法时通知控制者 之后会重新询问
+    fn read_choice_error(&mut self, choice: &ChoiceReq, choice_error: &ChoiceError);
+    // chooser 为做出选择的玩家 player_id 为费用区所属的玩家
+    fn read_reuse_choice(
+        &mut self,
+        chooser: PlayerId,
+        player_id: PlayerId,
+        limit: usize,
+        deadline: Option<Deadline>,
+    ) -> Vec<EntryId>;
 
     fn help_main(&self);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PlayerAction {
     // 放置卡片
     SetCard {
@@ -0,0 +1,270 @@
+use crate::card::Card;
+use crate::common::{CardInfoId, EntryId, PlayerId};
+use crate::game::GamePhase;
+use crate::tr;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 一张卡在某个玩家眼中的样子 看不到的卡只有实体id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardView {
+    Hidden {
+        entry_id: EntryId,
+    },
+    Visible {
+        entry_id: EntryId,
+        card_id: CardInfoId,
+        name: String,
+        cost: usize,
+        ack: usize,
+        owner: PlayerId,
+        controller: PlayerId,
+        attack_counter: usize,
+    },
+}
+
+/// 观察者 决定可以看到哪些信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Observer {
+    // 参与对局的玩家 可以看到自己的手卡和自己控制的背面朝上的卡
+    Player(PlayerId),
+    // 观战者的公开信息 看不到任何手卡和背面朝上的卡
+    Public,
+    // 全部信息 包括手卡、背面朝上的卡和卡组顺序
+    Full,
+}
+
+impl Observer {
+    // 是否可以看到玩家的手卡
+    pub fn sees_hand(&self, player_id: PlayerId) -> bool {
+        match self {
+            Observer::Player(viewer) => *viewer == player_id,
+            Observer::Public => false,
+            Observer::Full => true,
+        }
+    }
+
+    // 背面朝上的卡只有控制者可以看到
+    pub fn sees_card(&self, card: &Card) -> bool {
+        !card.face_down || self.sees_hand(card.controller)
+    }
+}
+
+impl CardView {
+    pub fn of(card: &Card, observer: &Observer) -> CardView {
+        if !observer.sees_card(card) {
+            return CardView::Hidden {
+                entry_id: card.entry_id,
+            };
+        }
+        CardView::Visible {
+            entry_id: card.entry_id,
+            card_id: card.card_info.id().to_string(),
+            name: card.card_info.name.clone(),
+            cost: card.card_info.cost,
+            ack: card.card_info.ack,
+            owner: card.owner,
+            controller: card.controller,
+            attack_counter: card.attack_counter,
+        }
+    }
+
+    pub fn entry_id(&self) -> EntryId {
+        match self {
+            CardView::Hidden { entry_id } | CardView::Visible { entry_id, .. } => *entry_id,
+        }
+    }
+
+    // 看不到的卡没有费用
+    pub fn cost(&self) -> Option<usize> {
+        match self {
+            CardView::Hidden { .. } => None,
+            CardView::Visible { cost, .. } => Some(*cost),
+        }
+    }
+}
+
+impl fmt::Display for CardView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardView::Hidden { entry_id } => write!(f, "{}", tr!("[{}] 背面朝上", entry_id)),
+            CardView::Visible {
+                entry_id,
+                name,
+                cost,
+                ack,
+                ..
+            } => write!(
+                f,
+                "{}",
+                tr!("[{}] {} 费用{} 攻击力{}", entry_id, name, cost, ack)
+            ),
+        }
+    }
+}
+
+/// 前场或后场的一个区域
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneView {
+    pub id: EntryId,
+    pub front: bool,
+    pub cards: Vec<CardView>,
+}
+
+/// 一位玩家的公开信息 手卡只有本人可以看到
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatView {
+    pub player_id: PlayerId,
+    pub alive: bool,
+    pub hp: usize,
+    pub real_point: usize,
+    // 其他玩家的手卡为 None
+    pub hand: Option<Vec<CardView>>,
+    pub hand_count: usize,
+    // 卡组只公开张数 全部信息时为卡组顺序
+    pub desk: Option<Vec<CardView>>,
+    pub desk_count: usize,
+    pub cost: Vec<CardView>,
+    pub grave: Vec<CardView>,
+    pub zones: Vec<ZoneView>,
+}
+
+impl SeatView {
+    pub fn front_zones(&self) -> impl Iterator<Item = &ZoneView> {
+        self.zones.iter().filter(|zone| zone.front)
+    }
+}
+
+/// 某个观察者可以看到的游戏状态 命令行、AI、观战和网络客户端只通过它读取对局
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub observer: Observer,
+    pub turn: usize,
+    pub current_player: PlayerId,
+    pub phase: GamePhase,
+    pub game_over: bool,
+    // 按座位顺序排列
+    pub seats: Vec<SeatView>,
+}
+
+impl PlayerView {
+    pub fn seat(&self, player_id: PlayerId) -> &SeatView {
+        &self.seats[player_id]
+    }
+
+    // 按实体id查找可以看到的卡 看不到或背面朝上时返回 None
+    pub fn find_card(&self, entry_id: EntryId) -> Option<&CardView> {
+        self.seats
+            .iter()
+            .flat_map(|seat| {
+                seat.hand
+                    .iter()
+                    .chain(seat.desk.iter())
+                    .flatten()
+                    .chain(&seat.cost)
+                    .chain(&seat.grave)
+                    .chain(seat.zones.iter().flat_map(|zone| &zone.cards))
+            })
+            .find(|card| matches!(card, CardView::Visible { entry_id: id, .. } if *id == entry_id))
+    }
+}
+
+// 一组卡 每张一行
+fn write_cards(f: &mut fmt::Formatter<'_>, cards: &[CardView]) -> fmt::Result {
+    for card in cards {
+        write!(f, "\n    {}", card)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for PlayerView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                "第{}回合 玩家[{}] {}阶段",
+                self.turn,
+                self.current_player,
+                crate::card_text::describe_phase(&self.phase)
+            )
+        )?;
+        for seat in &self.seats {
+            write!(
+                f,
+                "\n{}",
+                tr!(
+                    "玩家[{}] HP:{} RealPoint:{} 手卡:{} 卡组:{}",
+                    seat.player_id,
+                    seat.hp,
+                    seat.real_point,
+                    seat.hand_count,
+                    seat.desk_count
+                )
+            )?;
+            if !seat.alive {
+                write!(f, " {}", tr!("已淘汰"))?;
+            }
+            if let Some(hand) = &seat.hand {
+                write!(f, "\n  {}", tr!("手卡"))?;
+                write_cards(f, hand)?;
+            }
+            for zone in &seat.zones {
+                let name = if zone.front {
+                    tr!("前场[{}]", zone.id)
+                } else {
+                    tr!("后场[{}]", zone.id)
+                };
+                write!(f, "\n  {}", name)?;
+                write_cards(f, &zone.cards)?;
+            }
+            write!(f, "\n  {}", tr!("Cost区"))?;
+            write_cards(f, &seat.cost)?;
+            write!(f, "\n  {}", tr!("墓地"))?;
+            write_cards(f, &seat.grave)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardInfoBuilder;
+
+    #[test]
+    fn test_face_down_card_is_hidden() {
+        let card = Card {
+            entry_id: 7,
+            card_info: CardInfoBuilder::new("secret".to_string()).build(),
+            owner: 0,
+            controller: 0,
+            attack_counter: 0,
+            attack_max: 1,
+            face_down: true,
+        };
+        // 对手和公开观战只看到实体id
+        for observer in [Observer::Player(1), Observer::Public] {
+            assert_eq!(
+                CardView::of(&card, &observer),
+                CardView::Hidden { entry_id: 7 }
+            );
+        }
+        // 控制者和全部信息可以看到
+        for observer in [Observer::Player(0), Observer::Full] {
+            assert!(matches!(
+                CardView::of(&card, &observer),
+                CardView::Visible { card_id, .. } if card_id == "secret"
+            ));
+        }
+        // 正面朝上的卡所有人都可以看到
+        let card = Card {
+            face_down: false,
+            ..card
+        };
+        assert!(matches!(
+            CardView::of(&card, &Observer::Public),
+            CardView::Visible { .. }
+        ));
+    }
+}
@@ -0,0 +1,529 @@
+use crate::choice_error::ChoiceError;
+use crate::choice_req::ChoiceReq;
+use crate::choice_res::ChoiceRes;
+use crate::common::PlayerId;
+use crate::game::{Game, GamePhase};
+use crate::i18n::Dbg;
+use crate::legal_actions::ChoiceOptions;
+use crate::player_actions::PlayerAction;
+use crate::player_view::PlayerView;
+use crate::time_control::Deadline;
+use crate::tr;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// 发给远程玩家的消息 每条一行JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    // 连接后告诉客户端自己的座位
+    Welcome {
+        player_id: PlayerId,
+    },
+    // 该玩家可以看到的游戏状态 每次询问前和游戏结束时发送
+    View {
+        view: PlayerView,
+    },
+    // 等待主要阶段或战斗阶段的操作 回复 Action
+    Action {
+        player_id: PlayerId,
+        phase: GamePhase,
+        options: Vec<PlayerAction>,
+    },
+    // 等待选择 回复 Choice
+    Choice {
+        player_id: PlayerId,
+        choice: ChoiceReq,
+        options: ChoiceOptions,
+    },
+    // 上一条回复不合法 之后会重新询问
+    Error {
+        message: String,
+    },
+}
+
+/// 远程玩家的回复
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Action { action: PlayerAction },
+    Choice { choice: ChoiceRes },
+}
+
+// 一条连接 回复在单独的线程中按行读取 读取时可以设置时限
+struct Connection {
+    lines: Receiver<io::Result<String>>,
+    writer: Box<dyn Write + Send>,
+    // TCP连接关闭时需要同时结束读取的线程
+    stream: Option<TcpStream>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(stream) = &self.stream {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// 通过JSON行协议控制一个座位的远程玩家 代替命令行输入
+#[derive(Clone)]
+pub struct RemoteSeat(Arc<Mutex<Connection>>);
+
+impl fmt::Debug for RemoteSeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RemoteSeat")
+    }
+}
+
+impl RemoteSeat {
+    pub fn new(reader: impl BufRead + Send + 'static, writer: impl Write + Send + 'static) -> Self {
+        RemoteSeat::with_stream(reader, writer, None)
+    }
+
+    pub fn tcp(stream: TcpStream) -> io::Result<Self> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let writer = stream.try_clone()?;
+        Ok(RemoteSeat::with_stream(reader, writer, Some(stream)))
+    }
+
+    fn with_stream(
+        reader: impl BufRead + Send + 'static,
+        writer: impl Write + Send + 'static,
+        stream: Option<TcpStream>,
+    ) -> Self {
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let failed = line.is_err();
+                if sender.send(line).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        RemoteSeat(Arc::new(Mutex::new(Connection {
+            lines,
+            writer: Box::new(writer),
+            stream,
+        })))
+    }
+
+    pub fn send(&self, message: &ServerMessage) -> io::Result<()> {
+        let mut connection = self.0.lock().unwrap();
+        serde_json::to_writer(&mut connection.writer, message)?;
+        writeln!(connection.writer)?;
+        connection.writer.flush()
+    }
+
+    /// 读取一条回复 到达 `end` 时为 `TimedOut`，连接关闭时为 `UnexpectedEof`，格式错误时为 `InvalidData`
+    pub fn receive(&self, end: Option<Instant>) -> io::Result<ClientMessage> {
+        let connection = self.0.lock().unwrap();
+        let line = match end {
+            Some(end) => connection
+                .lines
+                .recv_timeout(end.saturating_duration_since(Instant::now()))
+                .map_err(|e| match e {
+                    RecvTimeoutError::Timeout => io::Error::from(io::ErrorKind::TimedOut),
+                    RecvTimeoutError::Disconnected => io::ErrorKind::UnexpectedEof.into(),
+                }),
+            None => connection
+                .lines
+                .recv()
+                .map_err(|_| io::ErrorKind::UnexpectedEof.into()),
+        }??;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// 丢弃还没有读取的回复 超时后迟到的回复不会作为下一次询问的回答
+    pub fn discard_pending(&self) {
+        self.0.lock().unwrap().lines.try_iter().for_each(drop);
+    }
+}
+
+/// 为每个没有控制者的座位接受一个连接 按连接顺序分配座位
+pub fn accept_players(game: &mut Game, listener: &TcpListener) -> io::Result<()> {
+    for player_id in 0..game.player_count() {
+        if game.is_ai_player(player_id) || game.is_remote_player(player_id) {
+            continue;
+        }
+        info!("{}", tr!("等待玩家[{}]连接", player_id));
+        let (stream, addr) = listener.accept()?;
+        let seat = RemoteSeat::tcp(stream)?;
+        seat.send(&ServerMessage::Welcome { player_id })?;
+        info!("{}", tr!("玩家[{}]已连接 {}", player_id, addr));
+        game.set_remote_player(player_id, seat);
+    }
+    Ok(())
+}
+
+/// 不是AI的座位都由标准输入输出按同样的协议控制 通过消息中的 `player_id` 区分座位
+pub fn attach_stdio(game: &mut Game) -> io::Result<()> {
+    let seat = RemoteSeat::new(BufReader::new(io::stdin()), io::stdout());
+    for player_id in 0..game.player_count() {
+        if game.is_ai_player(player_id) {
+            continue;
+        }
+        seat.send(&ServerMessage::Welcome { player_id })?;
+        game.set_remote_player(player_id, seat.clone());
+    }
+    Ok(())
+}
+
+impl Game {
+    // 发送一条消息 失败时只记录 之后的读取会发现连接已断开
+    fn remote_send(&self, seat: &RemoteSeat, message: ServerMessage) {
+        if let Err(e) = seat.send(&message) {
+            error!("{}", tr!("发送给远程玩家失败: {}", e));
+        }
+    }
+
+    // 发送玩家视角和请求后读取回复 格式错误时重新读取 超时或连接断开时返回 None
+    fn remote_ask(
+        &mut self,
+        seat: &RemoteSeat,
+        player_id: PlayerId,
+        request: ServerMessage,
+        deadline: Option<Deadline>,
+    ) -> Option<ClientMessage> {
+        // 时间已经用完 不再询问
+        if deadline.is_some_and(|deadline| deadline.expired(Instant::now())) {
+            self.time_out(player_id);
+            return None;
+        }
+        seat.discard_pending();
+        self.remote_send(
+            seat,
+            ServerMessage::View {
+                view: self.player_view(player_id),
+            },
+        );
+        self.remote_send(seat, request);
+        loop {
+            match seat.receive(deadline.map(|deadline| deadline.end)) {
+                Ok(message) => return Some(message),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    self.time_out(player_id);
+                    self.remote_send(
+                        seat,
+                        ServerMessage::Error {
+                            message: tr!("超时，使用默认操作"),
+                        },
+                    );
+                    return None;
+                }
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    self.remote_send(
+                        seat,
+                        ServerMessage::Error {
+                            message: e.to_string(),
+                        },
+                    );
+                }
+                Err(e) => {
+                    error!("{}", tr!("远程玩家[{}]断开连接: {}", player_id, e));
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// 读取远程玩家在主要阶段或战斗阶段的操作 直到回复 Pass
+    pub fn remote_read_action(&mut self) {
+        let player_id = self.current_player();
+        let phase = self.current_phase();
+        let Some(seat) = self.remote_player(player_id) else {
+            return;
+        };
+        // 不合法的回复不重新计时
+        let mut deadline = self.decision_deadline(player_id);
+        while self.current_phase() == phase && !self.is_game_over() {
+            let options = self.legal_actions();
+            let request = ServerMessage::Action {
+                player_id,
+                phase,
+                options: options.clone(),
+            };
+            let action = match self.remote_ask(&seat, player_id, request, deadline) {
+                Some(ClientMessage::Action { action }) => action,
+                Some(ClientMessage::Choice { .. }) => {
+                    self.remote_send(
+                        &seat,
+                        ServerMessage::Error {
+                            message: tr!("当前需要操作而不是选择"),
+                        },
+                    );
+                    continue;
+                }
+                None => break,
+            };
+            if action == PlayerAction::Pass {
+                self.finish_decision(deadline);
+                break;
+            }
+            // 只执行询问时列出的操作
+            if options.contains(&action) {
+                self.finish_decision(deadline);
+                self.deal_player_action(action);
+                deadline = self.decision_deadline(player_id);
+            } else {
+                self.remote_send(
+                    &seat,
+                    ServerMessage::Error {
+                        message: tr!("当前不能执行 {}", Dbg(&action)),
+                    },
+                );
+            }
+        }
+    }
+
+    /// 向远程玩家发起一次选择 回答由 `ask_choice` 检查 超时或连接断开时返回 None
+    pub fn remote_read_choice(
+        &mut self,
+        player_id: PlayerId,
+        choice: &ChoiceReq,
+        deadline: Option<Deadline>,
+    ) -> Option<Result<ChoiceRes, ChoiceError>> {
+        let seat = self.remote_player(player_id)?;
+        let request = ServerMessage::Choice {
+            player_id,
+            choice: choice.clone(),
+            options: self.choice_options(choice),
+        };
+        match self.remote_ask(&seat, player_id, request, deadline)? {
+            ClientMessage::Choice { choice: res } => Some(Ok(res)),
+            ClientMessage::Action { .. } => Some(Err(ChoiceError::WrongAnswer)),
+        }
+    }
+
+    pub fn remote_read_choice_error(&mut self, player_id: PlayerId, choice_error: &ChoiceError) {
+        if let Some(seat) = self.remote_player(player_id) {
+            self.remote_send(
+                &seat,
+                ServerMessage::Error {
+                    message: choice_error.to_string(),
+                },
+            );
+        }
+    }
+
+    /// 游戏结束时把最终状态发给所有远程玩家
+    pub fn remote_game_over(&self) {
+        for player_id in 0..self.player_count() {
+            if let Some(seat) = self.remote_player(player_id) {
+                self.remote_send(
+                    &seat,
+                    ServerMessage::View {
+                        view: self.player_view(player_id),
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ruleset::Ruleset;
+    use crate::test_util::{test_lua_api, test_players};
+    use std::collections::HashSet;
+    use std::thread;
+
+    // 所有操作都 pass 的客户端 第一次操作前先发送一行错误的数据 返回收到的全部消息
+    fn passing_client(addr: std::net::SocketAddr) -> thread::JoinHandle<Vec<ServerMessage>> {
+        thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut messages = Vec::new();
+            for line in BufReader::new(stream).lines() {
+                let message: ServerMessage = serde_json::from_str(&line.unwrap()).unwrap();
+                let reply = match &message {
+                    ServerMessage::Action { .. } => {
+                        if !messages
+                            .iter()
+                            .any(|m| matches!(m, ServerMessage::Action { .. }))
+                        {
+                            writeln!(writer, "not json").unwrap();
+                        }
+                        Some(ClientMessage::Action {
+                            action: PlayerAction::Pass,
+                        })
+                    }
+                    ServerMessage::Choice {
+                        choice: ChoiceReq::Mulligan(_),
+                        ..
+                    } => Some(ClientMessage::Choice {
+                        choice: ChoiceRes::Mulligan(Vec::new()),
+                    }),
+                    ServerMessage::Choice { .. } => Some(ClientMessage::Choice {
+                        choice: ChoiceRes::None,
+                    }),
+                    _ => None,
+                };
+                if let Some(reply) = reply {
+                    serde_json::to_writer(&mut writer, &reply).unwrap();
+                    writeln!(writer).unwrap();
+                }
+                messages.push(message);
+            }
+            messages
+        })
+    }
+
+    fn two_player_game() -> Game {
+        let players = test_players(2, 10);
+        Game::new(players, &test_lua_api(), HashSet::new(), Ruleset::default()).unwrap()
+    }
+
+    #[test]
+    fn test_remote_action_must_be_listed() {
+        let mut game = two_player_game();
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, mut reply_writer) = io::pipe().unwrap();
+        let seat = RemoteSeat::new(BufReader::new(reply_reader), request_writer);
+        game.set_remote_player(game.current_player(), seat);
+        // 依次回复不存在的卡、没有列出的操作和 Pass
+        let client = thread::spawn(move || {
+            let mut replies = vec![
+                PlayerAction::SetCard {
+                    card_id: 9999,
+                    zone_id: 0,
+                },
+                PlayerAction::EffectCard { card_id: 0 },
+                PlayerAction::Pass,
+            ]
+            .into_iter();
+            let mut errors = 0;
+            for line in BufReader::new(request_reader).lines() {
+                match serde_json::from_str(&line.unwrap()).unwrap() {
+                    ServerMessage::Action { .. } => {
+                        let action = replies.next().unwrap();
+                        serde_json::to_writer(&mut reply_writer, &ClientMessage::Action { action })
+                            .unwrap();
+                        writeln!(reply_writer).unwrap();
+                    }
+                    ServerMessage::Error { .. } => errors += 1,
+                    _ => {}
+                }
+            }
+            errors
+        });
+        game.remote_read_action();
+        drop(game);
+        assert_eq!(client.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_remote_choice_is_checked() {
+        let mut game = two_player_game();
+        let player_id = game.current_player();
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, mut reply_writer) = io::pipe().unwrap();
+        let seat = RemoteSeat::new(BufReader::new(reply_reader), request_writer);
+        game.set_remote_player(player_id, seat);
+        // 先回答不在手卡中的卡 收到错误后不放回任何卡
+        let client = thread::spawn(move || {
+            let mut replies = vec![vec![9999], Vec::new()].into_iter();
+            let mut errors = 0;
+            for line in BufReader::new(request_reader).lines() {
+                match serde_json::from_str(&line.unwrap()).unwrap() {
+                    ServerMessage::Choice { .. } => {
+                        let choice = ChoiceRes::Mulligan(replies.next().unwrap());
+                        serde_json::to_writer(&mut reply_writer, &ClientMessage::Choice { choice })
+                            .unwrap();
+                        writeln!(reply_writer).unwrap();
+                    }
+                    ServerMessage::Error { .. } => errors += 1,
+                    _ => {}
+                }
+            }
+            errors
+        });
+        let res = game.ask_choice(player_id, ChoiceReq::Mulligan(player_id));
+        assert_eq!(res, ChoiceRes::Mulligan(Vec::new()));
+        drop(game);
+        assert_eq!(client.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_remote_disconnect_uses_default_choice() {
+        let mut game = two_player_game();
+        let player_id = game.current_player();
+        let (request_reader, request_writer) = io::pipe().unwrap();
+        let (reply_reader, reply_writer) = io::pipe().unwrap();
+        let seat = RemoteSeat::new(BufReader::new(reply_reader), request_writer);
+        game.set_remote_player(player_id, seat);
+        // 客户端不回答就断开
+        drop(reply_writer);
+        let client = thread::spawn(move || {
+            BufReader::new(request_reader)
+                .lines()
+                .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+                .filter(|message| matches!(message, ServerMessage::Choice { .. }))
+                .count()
+        });
+        let res = game.ask_choice(player_id, ChoiceReq::Mulligan(player_id));
+        assert_eq!(res, ChoiceRes::Mulligan(Vec::new()));
+        drop(game);
+        // 只询问一次
+        assert_eq!(client.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_two_remote_players_over_tcp() {
+        let mut game = two_player_game();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let clients = [passing_client(addr), passing_client(addr)];
+        accept_players(&mut game, &listener).unwrap();
+        game.run();
+        assert!(game.is_game_over());
+        // 关闭连接 客户端读到结束后退出
+        drop(game);
+
+        // 两个客户端同时连接 座位以 Welcome 消息为准
+        let mut seats = HashSet::new();
+        for client in clients {
+            let messages = client.join().unwrap();
+            let ServerMessage::Welcome { player_id } = messages[0] else {
+                panic!("first message is not Welcome: {:?}", messages[0]);
+            };
+            assert!(seats.insert(player_id));
+            assert!(messages.iter().any(|m| matches!(
+                m,
+                ServerMessage::Choice {
+                    choice: ChoiceReq::Mulligan(id),
+                    options: ChoiceOptions::Mulligan { hand },
+                    ..
+                } if *id == player_id && hand.len() == 5
+            )));
+            assert!(
+                messages
+                    .iter()
+                    .any(|m| matches!(m, ServerMessage::Error { .. }))
+            );
+            let views: Vec<&PlayerView> = messages
+                .iter()
+                .filter_map(|m| match m {
+                    ServerMessage::View { view } => Some(view),
+                    _ => None,
+                })
+                .collect();
+            // 只能看到自己的手卡
+            assert!(views.iter().all(|view| {
+                view.seats
+                    .iter()
+                    .all(|seat| seat.hand.is_some() == (seat.player_id == player_id))
+            }));
+            assert!(views.last().unwrap().game_over);
+        }
+    }
+}
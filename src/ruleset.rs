@@ -0,0 +1,357 @@
+use crate::common::{CardInfoId, PlayerId};
+use crate::game::GamePhase;
+use mlua::{UserData, UserDataMethods, Variadic};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+/// 可配置的规则项名称
+const RULE_KEYS: [&str; 16] = [
+    "max_hand_size",
+    "max_cost_size",
+    "max_real_point",
+    "start_hp",
+    "opening_hand",
+    "front_zone_count",
+    "back_zone_count",
+    "mulligan",
+    "teams",
+    "min_deck_size",
+    "max_deck_size",
+    "max_copies",
+    "turn_time",
+    "decision_time",
+    "reserve_time",
+    "max_timeouts",
+];
+
+/// 阶段列表类的规则项名称
+const PHASE_KEYS: [&str; 2] = ["phases", "skip_first_turn"];
+
+/// 卡片列表类的规则项名称 `S000-*` 表示前缀相同的所有卡片
+const CARD_KEYS: [&str; 3] = ["banned", "limited", "card_pool"];
+
+/// 游戏规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ruleset {
+    // 规则名称
+    pub name: String,
+    // 手卡上限
+    pub max_hand_size: usize,
+    // Cost区上限
+    pub max_cost_size: usize,
+    // RealPoint上限
+    pub max_real_point: usize,
+    // 初始生命值
+    pub start_hp: usize,
+    // 起手卡数
+    pub opening_hand: usize,
+    // 前场区域数
+    pub front_zone_count: usize,
+    // 后场区域数
+    pub back_zone_count: usize,
+    // 起手调度次数 0为不调度
+    pub mulligan: usize,
+    // 队伍数 0为各自为战 否则玩家按座位轮流分入各队
+    pub teams: usize,
+    // 卡组最少卡数
+    pub min_deck_size: usize,
+    // 卡组最多卡数 0为不限制
+    pub max_deck_size: usize,
+    // 同名卡最多张数 0为不限制
+    pub max_copies: usize,
+    // 禁止使用的卡片
+    pub banned: Vec<CardInfoId>,
+    // 只能放入1张的卡片
+    pub limited: Vec<CardInfoId>,
+    // 可以使用的卡片 为空时不限制
+    pub card_pool: Vec<CardInfoId>,
+    // 每个回合依次进行的阶段
+    pub phases: Vec<GamePhase>,
+    // 游戏第一个回合跳过的阶段
+    pub skip_first_turn: Vec<GamePhase>,
+    // 每回合操作的时间限制（秒） 0为不限制
+    #[serde(default)]
+    pub turn_time: usize,
+    // 每次决定的时间限制（秒） 0为不限制
+    #[serde(default)]
+    pub decision_time: usize,
+    // 备用时间（秒） 超过限制后先消耗备用时间 用完后超时
+    #[serde(default)]
+    pub reserve_time: usize,
+    // 超时次数达到后判负 0为不判负
+    #[serde(default)]
+    pub max_timeouts: usize,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            name: "standard".to_string(),
+            max_hand_size: 20,
+            max_cost_size: 6,
+            max_real_point: 6,
+            start_hp: 6,
+            opening_hand: 5,
+            front_zone_count: 4,
+            back_zone_count: 4,
+            mulligan: 1,
+            teams: 0,
+            min_deck_size: 0,
+            max_deck_size: 0,
+            max_copies: 0,
+            banned: Vec::new(),
+            limited: Vec::new(),
+            card_pool: Vec::new(),
+            phases: vec![
+                GamePhase::Start,
+                GamePhase::Draw,
+                GamePhase::Reuse,
+                GamePhase::Main,
+                GamePhase::Fight,
+                GamePhase::Main2,
+                GamePhase::End,
+            ],
+            skip_first_turn: Vec::new(),
+            turn_time: 0,
+            decision_time: 0,
+            reserve_time: 0,
+            max_timeouts: 0,
+        }
+    }
+}
+
+impl Ruleset {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+
+    // 按名称设置规则项
+    pub fn set(&mut self, key: &str, value: usize) -> Result<(), String> {
+        match key {
+            "max_hand_size" => self.max_hand_size = value,
+            "max_cost_size" => self.max_cost_size = value,
+            "max_real_point" => self.max_real_point = value,
+            "start_hp" => self.start_hp = value,
+            "opening_hand" => self.opening_hand = value,
+            "front_zone_count" => self.front_zone_count = value,
+            "back_zone_count" => self.back_zone_count = value,
+            "mulligan" => self.mulligan = value,
+            "teams" if value == 1 => return Err("队伍数不能为1，各自为战请设为0".to_string()),
+            "teams" => self.teams = value,
+            "min_deck_size" => self.min_deck_size = value,
+            "max_deck_size" => self.max_deck_size = value,
+            "max_copies" => self.max_copies = value,
+            "turn_time" => self.turn_time = value,
+            "decision_time" => self.decision_time = value,
+            "reserve_time" => self.reserve_time = value,
+            "max_timeouts" => self.max_timeouts = value,
+            _ => return Err(format!("未知的规则项 {}", key)),
+        }
+        Ok(())
+    }
+
+    // 按名称设置阶段列表，阶段名用逗号或参数分隔
+    pub fn set_phases<S: AsRef<str>>(&mut self, key: &str, names: &[S]) -> Result<(), String> {
+        let mut phases = Vec::new();
+        for name in names {
+            let name = name.as_ref().trim();
+            if name.is_empty() {
+                continue;
+            }
+            match GamePhase::from_name(name) {
+                Some(phase) => phases.push(phase),
+                None => return Err(format!("未知的阶段 {}", name)),
+            }
+        }
+        match key {
+            "phases" => {
+                if phases.is_empty() {
+                    return Err("阶段列表不能为空".to_string());
+                }
+                self.phases = phases;
+            }
+            "skip_first_turn" => self.skip_first_turn = phases,
+            _ => return Err(format!("未知的规则项 {}", key)),
+        }
+        Ok(())
+    }
+
+    // 按名称设置卡片列表
+    pub fn set_cards<S: AsRef<str>>(&mut self, key: &str, ids: &[S]) -> Result<(), String> {
+        let ids = ids
+            .iter()
+            .map(|id| id.as_ref().trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        match key {
+            "banned" => self.banned = ids,
+            "limited" => self.limited = ids,
+            "card_pool" => self.card_pool = ids,
+            _ => return Err(format!("未知的规则项 {}", key)),
+        }
+        Ok(())
+    }
+
+    // 玩家所在的队伍 各自为战时每位玩家单独一队
+    pub fn team_of(&self, player_id: PlayerId) -> usize {
+        if self.teams == 0 {
+            player_id
+        } else {
+            player_id % self.teams
+        }
+    }
+
+    // 生成一个回合要进行的阶段
+    pub fn turn_phases(&self, first_turn: bool) -> VecDeque<GamePhase> {
+        self.phases
+            .iter()
+            .filter(|phase| !(first_turn && self.skip_first_turn.contains(phase)))
+            .copied()
+            .collect()
+    }
+
+    /// 解析 `key = value` 格式的规则文本，`#` 开头为注释
+    pub fn parse(name: String, text: &str) -> Result<Self, String> {
+        let mut ruleset = Ruleset::new(name);
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("第{}行格式错误: {}", index + 1, line));
+            };
+            let key = key.trim();
+            if PHASE_KEYS.contains(&key) {
+                let names: Vec<&str> = value.split(',').collect();
+                ruleset
+                    .set_phases(key, &names)
+                    .map_err(|e| format!("第{}行 {}", index + 1, e))?;
+                continue;
+            }
+            if CARD_KEYS.contains(&key) {
+                let ids: Vec<&str> = value.split(',').collect();
+                ruleset
+                    .set_cards(key, &ids)
+                    .map_err(|e| format!("第{}行 {}", index + 1, e))?;
+                continue;
+            }
+            let value = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("第{}行数值错误: {}", index + 1, line))?;
+            ruleset
+                .set(key, value)
+                .map_err(|e| format!("第{}行 {}", index + 1, e))?;
+        }
+        Ok(ruleset)
+    }
+
+    // 从规则文件读取，文件名即规则名称
+    pub fn load_file(path: &Path) -> Result<Self, String> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let text = fs::read_to_string(path).map_err(|e| format!("{:?}: {}", path, e))?;
+        Ruleset::parse(name, &text)
+    }
+}
+
+impl UserData for Ruleset {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // rules:max_hand_size(20) 等
+        for key in RULE_KEYS {
+            methods.add_method_mut(key, move |_, this, value: usize| {
+                this.set(key, value).map_err(mlua::Error::RuntimeError)
+            });
+        }
+        // rules:phases("start", "draw", "main", "end") 等
+        for key in PHASE_KEYS {
+            methods.add_method_mut(key, move |_, this, names: Variadic<String>| {
+                this.set_phases(key, &names)
+                    .map_err(mlua::Error::RuntimeError)
+            });
+        }
+        // rules:banned("S000-A-001", "S001-*") 等
+        for key in CARD_KEYS {
+            methods.add_method_mut(key, move |_, this, ids: Variadic<String>| {
+                this.set_cards(key, &ids).map_err(mlua::Error::RuntimeError)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides_defaults() {
+        let text = "# 快速对局\nstart_hp = 3\nopening_hand=4\n\n";
+        let ruleset = Ruleset::parse("quick".to_string(), text).unwrap();
+        assert_eq!(ruleset.name, "quick");
+        assert_eq!(ruleset.start_hp, 3);
+        assert_eq!(ruleset.opening_hand, 4);
+        assert_eq!(ruleset.max_hand_size, Ruleset::default().max_hand_size);
+    }
+
+    #[test]
+    fn test_parse_time_control() {
+        let text = "turn_time = 120\ndecision_time = 30\nreserve_time = 300\nmax_timeouts = 3";
+        let ruleset = Ruleset::parse("timed".to_string(), text).unwrap();
+        assert_eq!(
+            (
+                ruleset.turn_time,
+                ruleset.decision_time,
+                ruleset.reserve_time,
+                ruleset.max_timeouts
+            ),
+            (120, 30, 300, 3)
+        );
+        assert_eq!(Ruleset::default().turn_time, 0);
+    }
+
+    #[test]
+    fn test_parse_phases() {
+        let text = "phases = start, draw, standby, main, end\nskip_first_turn = draw";
+        let ruleset = Ruleset::parse("short".to_string(), text).unwrap();
+        assert_eq!(
+            ruleset.phases,
+            vec![
+                GamePhase::Start,
+                GamePhase::Draw,
+                GamePhase::Standby,
+                GamePhase::Main,
+                GamePhase::End
+            ]
+        );
+        assert_eq!(ruleset.skip_first_turn, vec![GamePhase::Draw]);
+        assert!(!ruleset.turn_phases(true).contains(&GamePhase::Draw));
+        assert!(ruleset.turn_phases(false).contains(&GamePhase::Draw));
+    }
+
+    #[test]
+    fn test_team_of() {
+        let mut ruleset = Ruleset::default();
+        assert_ne!(ruleset.team_of(0), ruleset.team_of(2));
+        ruleset.teams = 2;
+        assert_eq!(ruleset.team_of(0), ruleset.team_of(2));
+        assert_ne!(ruleset.team_of(1), ruleset.team_of(2));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_key() {
+        assert!(Ruleset::parse("bad".to_string(), "phases = start, lunch").is_err());
+        assert!(Ruleset::parse("bad".to_string(), "phases = ").is_err());
+        assert!(Ruleset::parse("bad".to_string(), "max_hp = 3").is_err());
+        assert!(Ruleset::parse("bad".to_string(), "start_hp = -1").is_err());
+        assert!(Ruleset::parse("bad".to_string(), "start_hp").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::load_error::{LoadError, LoadReport};
+use crate::lua_api::LuaApi;
+use crate::ruleset::Ruleset;
+use crate::sandbox::run_limited;
+use mlua::Lua;
+use std::fs;
+use std::path::Path;
+
+/// 读取 rulesets 目录：`.lua` 文件通过 `define_ruleset` 注册，其他文件按 `key = value` 解析
+pub fn load_rulesets(
+    lua: &Lua,
+    api: &mut LuaApi,
+    report: &mut LoadReport,
+) -> Result<(), LoadError> {
+    let dir = Path::new("rulesets");
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let files = match fs::read_dir(dir) {
+        Ok(files) => files,
+        Err(e) => return report.fail(LoadError::io(dir, e)),
+    };
+    for file in files.flatten() {
+        let path = file.path();
+        if !path.is_file() {
+            continue;
+        }
+        let result = if path.extension().is_some_and(|ext| ext == "lua") {
+            fs::read_to_string(&path)
+                .map_err(|e| LoadError::io(&path, e))
+                .and_then(|code| {
+                    api.load_with(lua, || {
+                        run_limited(lua, || {
+                            lua.load(&code)
+                                .set_name(format!("@{}", path.display()))
+                                .exec()
+                        })
+                    })
+                    .map_err(|e| LoadError::script(&path, None, &e))
+                })
+        } else {
+            Ruleset::load_file(&path)
+                .map(|ruleset| {
+                    api.rulesets.insert(ruleset.name.clone(), ruleset);
+                })
+                .map_err(|message| LoadError::Ruleset {
+                    path: path.clone(),
+                    message,
+                })
+        };
+        match result {
+            Ok(()) => report.loaded.push(path),
+            Err(load_error) => report.fail(load_error)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use mlua::{
+    Function, HookTriggers, Lua, LuaOptions, MultiValue, StdLib, Table, Value, Variadic, VmState,
+};
+
+// 每次检查指令数之间执行的指令数
+const HOOK_INTERVAL: u32 = 1000;
+
+// 沙盒中不能使用的全局函数 可以读取或执行其他文件
+const BLOCKED_GLOBALS: [&str; 3] = ["loadfile", "dofile", "require"];
+
+/// 卡片脚本的运行限制 每个脚本和每个效果回调分别计算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxLimits {
+    // 最多执行的指令数
+    pub instructions: u64,
+    // 最多新增使用的内存 字节
+    pub memory: usize,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            instructions: 1_000_000,
+            memory: 16 * 1024 * 1024,
+        }
+    }
+}
+
+// 当前脚本剩余的指令数
+struct Budget(u64);
+
+/// 创建只包含安全标准库的Lua环境 没有 os/io/debug/package 和读取文件的函数
+pub fn new_lua(limits: SandboxLimits) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::COROUTINE | StdLib::TABLE | StdLib::STRING | StdLib::UTF8 | StdLib::MATH,
+        LuaOptions::default(),
+    )?;
+    for name in BLOCKED_GLOBALS {
+        lua.globals().set(name, mlua::Nil)?;
+    }
+    // 二进制代码可以绕过检查 去掉 string.dump 并且 load 只接受文本
+    let string: Table = lua.globals().get("string")?;
+    string.set("dump", mlua::Nil)?;
+    let load: Function = lua.globals().get("load")?;
+    let text_load = lua.create_function(move |lua, args: Variadic<Value>| {
+        let mut args: Vec<Value> = args.into_iter().collect();
+        args.resize(args.len().max(3), mlua::Nil);
+        args[2] = Value::String(lua.create_string("t")?);
+        load.call::<MultiValue>(MultiValue::from_vec(args))
+    })?;
+    lua.globals().set("load", text_load)?;
+    lua.set_app_data(limits);
+    lua.set_global_hook(
+        HookTriggers::new().every_nth_instruction(HOOK_INTERVAL),
+        |lua, _debug| {
+            if let Some(mut budget) = lua.app_data_mut::<Budget>() {
+                if budget.0 < HOOK_INTERVAL as u64 {
+                    return Err(mlua::Error::RuntimeError(
+                        "脚本执行的指令数超过限制".to_string(),
+                    ));
+                }
+                budget.0 -= HOOK_INTERVAL as u64;
+            }
+            Ok(VmState::Continue)
+        },
+    )?;
+    Ok(lua)
+}
+
+/// 在限制下运行一段脚本或回调 结束后恢复外层的限制
+pub fn run_limited<R>(lua: &Lua, f: impl FnOnce() -> mlua::Result<R>) -> mlua::Result<R> {
+    let Some(limits) = lua.app_data_ref::<SandboxLimits>().map(|limits| *limits) else {
+        // 不是沙盒环境
+        return f();
+    };
+    // 嵌套运行时不能超过外层剩余的限制
+    let outer_budget = lua.app_data_ref::<Budget>().map(|budget| budget.0);
+    let instructions =
+        outer_budget.map_or(limits.instructions, |left| left.min(limits.instructions));
+    lua.set_app_data(Budget(instructions));
+    let outer_memory = lua.set_memory_limit(0)?;
+    let memory = lua.used_memory() + limits.memory;
+    let memory = if outer_memory == 0 {
+        memory
+    } else {
+        memory.min(outer_memory)
+    };
+    lua.set_memory_limit(memory)?;
+    let result = f();
+    lua.set_memory_limit(outer_memory)?;
+    let left = lua.remove_app_data::<Budget>().map_or(0, |budget| budget.0);
+    // 内层使用的指令数也从外层扣除
+    if let Some(outer) = outer_budget {
+        lua.set_app_data(Budget(outer - (instructions - left)));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exec(lua: &Lua, code: &str) -> mlua::Result<()> {
+        run_limited(lua, || lua.load(code).exec())
+    }
+
+    #[test]
+    fn test_unsafe_libraries_removed() {
+        let lua = new_lua(SandboxLimits::default()).unwrap();
+        assert!(exec(&lua, "os.execute('true')").is_err());
+        assert!(exec(&lua, "io.open('Cargo.toml')").is_err());
+        assert!(exec(&lua, "debug.getinfo(1)").is_err());
+        assert!(exec(&lua, "loadfile('cards/x.lua')").is_err());
+        assert!(exec(&lua, "local s = string.rep('a', 3) .. math.max(1, 2)").is_ok());
+    }
+
+    #[test]
+    fn test_instruction_limit() {
+        let limits = SandboxLimits {
+            instructions: 10_000,
+            ..Default::default()
+        };
+        let lua = new_lua(limits).unwrap();
+        assert!(exec(&lua, "while true do end").is_err());
+        // 超出限制后下一个脚本重新计算
+        assert!(exec(&lua, "local a = 0 for i = 1, 100 do a = a + i end").is_ok());
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let limits = SandboxLimits {
+            memory: 1024 * 1024,
+            ..Default::default()
+        };
+        let lua = new_lua(limits).unwrap();
+        assert!(exec(&lua, "local t = {} for i = 1, 1000000 do t[i] = i end").is_err());
+        assert!(exec(&lua, "local t = {} for i = 1, 10 do t[i] = i end").is_ok());
+    }
+
+    #[test]
+    fn test_binary_chunks_rejected() {
+        let lua = new_lua(SandboxLimits::default()).unwrap();
+        assert!(exec(&lua, "load(string.dump(function() end))").is_err());
+        assert!(exec(&lua, "assert(load('\\27Lua', 'x', 'b') == nil)").is_ok());
+        assert!(exec(&lua, "assert(load('return x', 'x', 't', {x = 1})() == 1)").is_ok());
+        assert!(exec(&lua, "x = 2 assert(load('return x')() == 2)").is_ok());
+    }
+
+    #[test]
+    fn test_nested_limits_share_budget() {
+        let limits = SandboxLimits {
+            instructions: 100_000,
+            ..Default::default()
+        };
+        let lua = new_lua(limits).unwrap();
+        // 每次嵌套调用都在限制内 但合计超过外层的限制
+        let nested = lua
+            .create_function(|lua, ()| {
+                run_limited(lua, || {
+                    lua.load("local a = 0 for i = 1, 20000 do a = a + i end")
+                        .exec()
+                })
+            })
+            .unwrap();
+        lua.globals().set("nested", nested).unwrap();
+        assert!(exec(&lua, "for i = 1, 20 do nested() end").is_err());
+        assert!(exec(&lua, "nested()").is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::game::Game;
+use crate::tr;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 存档格式的版本 存档结构变化时增加
+pub const SAVE_VERSION: u64 = 1;
+
+// 存档文件 版本号和完整的游戏状态
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u64,
+    game: &'a Game,
+}
+
+/// 保存或读取存档时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveError {
+    // 文件读写失败
+    Io { path: PathBuf, message: String },
+    // 存档内容格式错误
+    Format(String),
+    // 存档版本和当前程序不一致
+    Version { found: u64, expected: u64 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io { path, message } => {
+                write!(f, "{}", tr!("{} 读写失败: {}", path.display(), message))
+            }
+            SaveError::Format(message) => write!(f, "{}", tr!("存档格式错误: {}", message)),
+            SaveError::Version { found, expected } => write!(
+                f,
+                "{}",
+                tr!("存档版本为{}，当前只支持版本{}", found, expected)
+            ),
+        }
+    }
+}
+
+impl Game {
+    /// 把完整的游戏状态（阶段、全部区域、计数器、待处理的效果和随机数状态）写成JSON
+    pub fn save_string(&self) -> Result<String, SaveError> {
+        let save_file = SaveFile {
+            version: SAVE_VERSION,
+            game: self,
+        };
+        serde_json::to_string_pretty(&save_file).map_err(|e| SaveError::Format(e.to_string()))
+    }
+
+    /// 从存档恢复游戏 卡片目录监视需要重新设置
+    pub fn from_save_string(text: &str) -> Result<Game, SaveError> {
+        let mut save_file: Value =
+            serde_json::from_str(text).map_err(|e| SaveError::Format(e.to_string()))?;
+        // 先检查版本 避免不同版本的结构差异报告成格式错误
+        let found = save_file
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| SaveError::Format("缺少版本号".to_string()))?;
+        if found != SAVE_VERSION {
+            return Err(SaveError::Version {
+                found,
+                expected: SAVE_VERSION,
+            });
+        }
+        let game = save_file
+            .get_mut("game")
+            .map(Value::take)
+            .ok_or_else(|| SaveError::Format("缺少游戏状态".to_string()))?;
+        serde_json::from_value(game).map_err(|e| SaveError::Format(e.to_string()))
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), SaveError> {
+        let text = self.save_string()?;
+        fs::write(path, text).map_err(|e| SaveError::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    pub fn load_from(path: &Path) -> Result<Game, SaveError> {
+        let text = fs::read_to_string(path).map_err(|e| SaveError::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        Game::from_save_string(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_other_version() {
+        assert_eq!(
+            Game::from_save_string(r#"{"version": 99, "game": {}}"#).unwrap_err(),
+            SaveError::Version {
+                found: 99,
+                expected: SAVE_VERSION,
+            }
+        );
+        assert!(matches!(
+            Game::from_save_string(r#"{"game": {}}"#),
+            Err(SaveError::Format(_))
+        ));
+        assert!(matches!(
+            Game::from_save_string(r#"{"version": 1, "game": {"turn_count": 1}}"#),
+            Err(SaveError::Format(_))
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::deck_check::DeckError;
+use crate::tr;
+use std::fmt;
+
+/// 无法开始游戏的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupError {
+    // 玩家的卡组不符合规则
+    Deck(DeckError),
+    // 分队时至少2队 并且少于玩家人数
+    Teams { teams: usize, players: usize },
+}
+
+impl From<DeckError> for SetupError {
+    fn from(e: DeckError) -> Self {
+        SetupError::Deck(e)
+    }
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupError::Deck(e) => write!(f, "{}", e),
+            SetupError::Teams { teams, players } => write!(
+                f,
+                "{}",
+                tr!(
+                    "{}人对局不能分为{}队，队伍数为0或至少2队并少于玩家人数",
+                    players,
+                    teams
+                )
+            ),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::player_view::PlayerView;
+use log::error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// 观战方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectatorMode {
+    // 实时的公开信息
+    Public,
+    // 延迟若干回合的全部信息
+    Delayed(usize),
+}
+
+#[derive(Debug)]
+struct Spectator {
+    mode: SpectatorMode,
+    sender: Sender<PlayerView>,
+    // 已经发送的全部信息记录数
+    sent: usize,
+}
+
+#[derive(Debug, Default)]
+struct HubState {
+    spectators: Vec<Spectator>,
+    // 全部信息的记录 延迟观战按回合数补发 所有人都收到的记录会删除
+    history: Vec<PlayerView>,
+    // history 第一条记录的序号
+    start: usize,
+}
+
+/// 对局的观战者 可以在对局进行中从其他线程加入
+#[derive(Debug, Clone, Default)]
+pub struct SpectatorHub(Arc<Mutex<HubState>>);
+
+impl SpectatorHub {
+    /// 加入观战 从加入后的下一次变化开始接收
+    pub fn attach(&self, mode: SpectatorMode) -> Receiver<PlayerView> {
+        let (sender, receiver) = channel();
+        let mut state = self.0.lock().unwrap();
+        let sent = state.start + state.history.len();
+        state.spectators.push(Spectator { mode, sender, sent });
+        receiver
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().unwrap().spectators.is_empty()
+    }
+
+    /// 发送对局的变化 `public` 为公开信息 `full` 为全部信息
+    ///
+    /// 对局结束时不再需要隐藏信息 延迟观战者立即收到全部记录
+    pub fn publish(&self, public: PlayerView, full: PlayerView) {
+        let mut state = self.0.lock().unwrap();
+        let turn = full.turn;
+        let game_over = full.game_over;
+        // 只有延迟观战者需要记录
+        if state
+            .spectators
+            .iter()
+            .any(|spectator| matches!(spectator.mode, SpectatorMode::Delayed(_)))
+        {
+            state.history.push(full);
+        }
+        let HubState {
+            spectators,
+            history,
+            start,
+        } = &mut *state;
+        // 断开的观战者在发送失败时移除
+        spectators.retain_mut(|spectator| match spectator.mode {
+            SpectatorMode::Public => spectator.sender.send(public.clone()).is_ok(),
+            SpectatorMode::Delayed(delay) => {
+                let ready = history
+                    .iter()
+                    .skip(spectator.sent - *start)
+                    .take_while(|view| game_over || view.turn + delay <= turn);
+                for view in ready {
+                    if spectator.sender.send(view.clone()).is_err() {
+                        return false;
+                    }
+                    spectator.sent += 1;
+                }
+                true
+            }
+        });
+        // 删除所有延迟观战者都已经收到的记录
+        let received = spectators
+            .iter()
+            .filter(|spectator| matches!(spectator.mode, SpectatorMode::Delayed(_)))
+            .map(|spectator| spectator.sent)
+            .min()
+            .unwrap_or(*start + history.len());
+        history.drain(..received - *start);
+        *start = received;
+    }
+
+    // 保存的记录数
+    #[cfg(test)]
+    fn history_len(&self) -> usize {
+        self.0.lock().unwrap().history.len()
+    }
+}
+
+/// 在新线程中把观战收到的状态逐行写成JSON 对局结束后线程退出
+pub fn write_views(receiver: Receiver<PlayerView>, path: &Path) -> io::Result<JoinHandle<()>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    Ok(thread::spawn(move || {
+        for view in receiver {
+            let written = serde_json::to_writer(&mut writer, &view)
+                .map_err(io::Error::from)
+                .and_then(|()| writeln!(writer))
+                .and_then(|()| writer.flush());
+            if let Err(e) = written {
+                error!("{}", e);
+                return;
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GamePhase;
+    use crate::player_view::Observer;
+
+    fn view(observer: Observer, turn: usize, game_over: bool) -> PlayerView {
+        PlayerView {
+            observer,
+            turn,
+            current_player: 0,
+            phase: GamePhase::Start,
+            game_over,
+            seats: Vec::new(),
+        }
+    }
+
+    fn publish(hub: &SpectatorHub, turn: usize, game_over: bool) {
+        hub.publish(
+            view(Observer::Public, turn, game_over),
+            view(Observer::Full, turn, game_over),
+        );
+    }
+
+    fn turns(receiver: &Receiver<PlayerView>) -> Vec<(Observer, usize)> {
+        receiver
+            .try_iter()
+            .map(|view| (view.observer, view.turn))
+            .collect()
+    }
+
+    #[test]
+    fn test_public_and_delayed_spectators() {
+        let hub = SpectatorHub::default();
+        let public = hub.attach(SpectatorMode::Public);
+        let delayed = hub.attach(SpectatorMode::Delayed(2));
+        publish(&hub, 1, false);
+        publish(&hub, 2, false);
+        assert_eq!(
+            turns(&public),
+            vec![(Observer::Public, 1), (Observer::Public, 2)]
+        );
+        assert!(turns(&delayed).is_empty());
+        publish(&hub, 3, false);
+        assert_eq!(turns(&delayed), vec![(Observer::Full, 1)]);
+        assert_eq!(hub.history_len(), 2);
+        // 结束时补发剩余的记录
+        publish(&hub, 4, true);
+        assert_eq!(
+            turns(&delayed),
+            vec![
+                (Observer::Full, 2),
+                (Observer::Full, 3),
+                (Observer::Full, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_late_attach_and_disconnect() {
+        let hub = SpectatorHub::default();
+        let first = hub.attach(SpectatorMode::Delayed(0));
+        publish(&hub, 1, false);
+        let late = hub.attach(SpectatorMode::Delayed(0));
+        publish(&hub, 2, false);
+        assert_eq!(turns(&late), vec![(Observer::Full, 2)]);
+        assert_eq!(
+            turns(&first),
+            vec![(Observer::Full, 1), (Observer::Full, 2)]
+        );
+        assert_eq!(hub.history_len(), 0);
+        drop(first);
+        drop(late);
+        publish(&hub, 3, false);
+        assert!(hub.is_empty());
+        assert_eq!(hub.history_len(), 0);
+    }
+}
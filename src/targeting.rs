@@ -1,8 +1,8 @@
-use crate::common::EntryId;
-use crate::effect::Condition;
+use crate::common::{EntryId, PlayerId};
+use serde::{Deserialize, Serialize};
 
 /// 目标
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Targeting {
     #[default]
     None,
@@ -10,16 +10,20 @@ pub enum Targeting {
     TargetPlayerSelf,
     // 对手玩家
     TargetPlayerOpponent,
+    // 指定的玩家
+    TargetPlayer(PlayerId),
     // 卡片
     TargetCard(EntryId),
     // 场地
     TargetZone(EntryId),
 }
 
+// 设计草稿 还没有接入效果
+#[allow(dead_code)]
 #[derive(Debug, Clone, Default, Copy, Eq, PartialEq)]
 pub enum Side {
     #[default]
-    BothSide,
+    Both,
     PlayerSelf,
     PlayerOpponent,
 }
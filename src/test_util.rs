@@ -0,0 +1,32 @@
+use crate::card::CardInfoBuilder;
+use crate::lua_api::LuaApi;
+use crate::player::{Player, PlayerDesk};
+
+/// 测试卡片的id
+pub const TEST_CARD: &str = "test-card";
+
+/// 只有一种测试卡片的 LuaApi
+pub fn test_card_api(cost: usize, ack: usize) -> LuaApi {
+    let mut api = LuaApi::new();
+    let mut card_info = CardInfoBuilder::new(TEST_CARD.to_string()).build();
+    card_info.cost = cost;
+    card_info.ack = ack;
+    api.cards.insert(TEST_CARD.to_string(), card_info);
+    api
+}
+
+/// 测试卡片没有费用和攻击力
+pub fn test_lua_api() -> LuaApi {
+    test_card_api(0, 0)
+}
+
+/// 卡组都是 desk_size 张测试卡片的玩家
+pub fn test_players(count: usize, desk_size: usize) -> Vec<Player> {
+    let desk = PlayerDesk(vec![TEST_CARD.to_string(); desk_size]);
+    (0..count)
+        .map(|id| Player {
+            id,
+            player_desk: desk.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,131 @@
+use crate::common::PlayerId;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 玩家剩余的备用时间和超时次数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerClock {
+    pub reserve: Duration,
+    // 判负用的超时次数 每回合最多记录一次
+    pub timeouts: usize,
+    // 最近记录超时的回合
+    #[serde(default)]
+    pub timeout_turn: Option<usize>,
+    // 包括同一回合中没有记录的超时 用来判断一次决定是否超时 不保存
+    #[serde(skip)]
+    pub expired: usize,
+}
+
+impl PlayerClock {
+    pub fn new(reserve_secs: usize) -> Self {
+        PlayerClock {
+            reserve: Duration::from_secs(reserve_secs as u64),
+            ..Default::default()
+        }
+    }
+
+    /// 决定结束 超过限制的时间从备用时间中扣除
+    pub fn spend(&mut self, deadline: &Deadline, now: Instant) {
+        let used = now.saturating_duration_since(deadline.limit);
+        self.reserve = self.reserve.saturating_sub(used);
+    }
+}
+
+/// 一次决定的时限 超过 `limit` 后开始消耗备用时间 到 `end` 时超时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    pub player_id: PlayerId,
+    pub limit: Instant,
+    pub end: Instant,
+}
+
+impl Deadline {
+    /// 没有时间限制也没有备用时间时为 None；只有备用时间时从现在开始消耗
+    pub fn new(
+        player_id: PlayerId,
+        now: Instant,
+        limit: Option<Instant>,
+        reserve: Duration,
+    ) -> Option<Deadline> {
+        if limit.is_none() && reserve.is_zero() {
+            return None;
+        }
+        let limit = limit.unwrap_or(now);
+        Some(Deadline {
+            player_id,
+            limit,
+            end: limit + reserve,
+        })
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.end.saturating_duration_since(now)
+    }
+
+    // 询问之前时间已经用完 如本回合的时间用完后进入下一个阶段
+    pub fn expired(&self, now: Instant) -> bool {
+        now >= self.end
+    }
+}
+
+// 标准输入在单独的线程中按行读取 读取时可以设置时限
+fn stdin_lines() -> &'static Mutex<Receiver<String>> {
+    static LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+/// 读取一行标准输入 到达 `end` 时返回 None；输入已经结束时返回空行
+pub fn read_stdin_line(end: Option<Instant>) -> Option<String> {
+    let lines = stdin_lines().lock().unwrap();
+    let result = match end {
+        Some(end) => lines.recv_timeout(end.saturating_duration_since(Instant::now())),
+        None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match result {
+        Ok(line) => Some(line),
+        Err(RecvTimeoutError::Timeout) => None,
+        Err(RecvTimeoutError::Disconnected) => Some(String::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_and_reserve() {
+        let now = Instant::now();
+        assert_eq!(Deadline::new(0, now, None, Duration::ZERO), None);
+        let limit = now + Duration::from_secs(30);
+        let deadline = Deadline::new(1, now, Some(limit), Duration::from_secs(60)).unwrap();
+        assert_eq!(deadline.end, limit + Duration::from_secs(60));
+        // 只有备用时间时从现在开始消耗
+        let clock_only = Deadline::new(1, now, None, Duration::from_secs(60)).unwrap();
+        assert_eq!(clock_only.limit, now);
+
+        let mut clock = PlayerClock::new(60);
+        clock.spend(&deadline, now + Duration::from_secs(10));
+        assert_eq!(clock.reserve, Duration::from_secs(60));
+        clock.spend(&deadline, limit + Duration::from_secs(25));
+        assert_eq!(clock.reserve, Duration::from_secs(35));
+        clock.spend(&clock_only, now + Duration::from_secs(100));
+        assert_eq!(clock.reserve, Duration::ZERO);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::card::CardInfo;
+use crate::effect::WindowsTag;
+use crate::lua_api::LuaApi;
+use crate::ruleset::Ruleset;
+use std::fmt;
+
+// 攻击力的合理上限
+const MAX_ACK: usize = 9999;
+
+/// 卡片数据检查发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CardIssue {
+    // 效果没有设置发动窗口
+    NoWindow {
+        card_id: String,
+        effect_id: String,
+    },
+    // 效果没有设置动作
+    NoAction {
+        card_id: String,
+        effect_id: String,
+    },
+    // 属性超出合理范围
+    StatOutOfRange {
+        card_id: String,
+        stat: &'static str,
+        value: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for CardIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardIssue::NoWindow { card_id, effect_id } => {
+                write!(f, "卡片[{}] 效果[{}]没有设置发动窗口", card_id, effect_id)
+            }
+            CardIssue::NoAction { card_id, effect_id } => {
+                write!(f, "卡片[{}] 效果[{}]没有设置动作", card_id, effect_id)
+            }
+            CardIssue::StatOutOfRange {
+                card_id,
+                stat,
+                value,
+                max,
+            } => write!(f, "卡片[{}] {}为{}，超过上限{}", card_id, stat, value, max),
+        }
+    }
+}
+
+/// 检查全部卡片 结果按卡片id排序
+pub fn check_cards(api: &LuaApi, ruleset: &Ruleset) -> Vec<CardIssue> {
+    let mut ids: Vec<&String> = api.cards.keys().collect();
+    ids.sort();
+    ids.into_iter()
+        .flat_map(|id| check_card(id, &api.cards[id], ruleset))
+        .collect()
+}
+
+// 检查一张卡片的属性和效果
+fn check_card(card_id: &str, card_info: &CardInfo, ruleset: &Ruleset) -> Vec<CardIssue> {
+    let mut issues = Vec::new();
+    // Cost区和RealPoint都用满也付不起的费用
+    let max_cost = ruleset.max_cost_size + ruleset.max_real_point;
+    for (stat, value, max) in [
+        ("cost", card_info.cost, max_cost),
+        ("ack", card_info.ack, MAX_ACK),
+    ] {
+        if value > max {
+            issues.push(CardIssue::StatOutOfRange {
+                card_id: card_id.to_string(),
+                stat,
+                value,
+                max,
+            });
+        }
+    }
+    for effect in &card_info.effects {
+        if effect.windows_tag == WindowsTag::None {
+            issues.push(CardIssue::NoWindow {
+                card_id: card_id.to_string(),
+                effect_id: effect.id().to_string(),
+            });
+        }
+        if effect.do_effect.is_empty() {
+            issues.push(CardIssue::NoAction {
+                card_id: card_id.to_string(),
+                effect_id: effect.id().to_string(),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mlua::Lua;
+
+    fn load(code: &str) -> Result<LuaApi, mlua::Error> {
+        let lua = Lua::new();
+        let mut api = LuaApi::new();
+        LuaApi::install(&lua)?;
+        api.load_with(&lua, || lua.load(code).exec())?;
+        Ok(api)
+    }
+
+    #[test]
+    fn test_check_card_issues() {
+        let api = load(
+            r#"
+            define_card("A", function(card)
+                card:cost(99)
+                card:reg_effect("e1", function(effect) end)
+            end)
+            define_card("B", function(card)
+                card:cost(2)
+                card:reg_effect("e1", function(effect)
+                    effect:window("set")
+                    effect:draw(1)
+                end)
+            end)
+            "#,
+        )
+        .unwrap();
+        let issues = check_cards(&api, &Ruleset::default());
+        assert_eq!(issues.len(), 3);
+        assert!(matches!(
+            issues[0],
+            CardIssue::StatOutOfRange { stat: "cost", .. }
+        ));
+        assert!(matches!(issues[1], CardIssue::NoWindow { .. }));
+        assert!(matches!(issues[2], CardIssue::NoAction { .. }));
+    }
+
+    #[test]
+    fn test_script_rejects_bad_definitions() {
+        // 重复定义
+        assert!(
+            load(r#"define_card("A", function(card) end) define_card("A", function(card) end)"#)
+                .is_err()
+        );
+        // 未知的窗口
+        assert!(
+            load(r#"define_card("A", function(card) card:reg_effect("e", function(e) e:window("lunch") end) end)"#)
+                .is_err()
+        );
+        // 负数属性
+        assert!(load(r#"define_card("A", function(card) card:cost(-1) end)"#).is_err());
+    }
+}
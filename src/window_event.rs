@@ -1,6 +1,9 @@
 use crate::common::EntryId;
+use crate::game::GamePhase;
 use crate::targeting::Targeting;
 
+// 设计草稿 目前只有部分事件被触发
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum WindowEvent {
     // 支付费用的事件
@@ -16,4 +19,8 @@ pub enum WindowEvent {
         source: Targeting,
         target: Targeting,
     },
+    // 进入阶段
+    Phase {
+        phase: GamePhase,
+    },
 }